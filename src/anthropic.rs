@@ -1,6 +1,12 @@
-use crate::client::{AIClient, ClientError, GenerateResponseResult, Result, UsageInfo};
+use crate::client::{
+  AIClient, ClientError, GenerateResponseResult, ReasoningEffort, ResponseFuture, Result, UsageInfo,
+};
+use crate::http;
+use crate::message::JsonSchema;
+use crate::models::estimate_tokens;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Debug, Serialize)]
 struct AnthropicRequest {
@@ -8,8 +14,37 @@ struct AnthropicRequest {
   max_tokens: u32,
   messages: Vec<Message>,
   stream: bool,
-  temperature: f32,
+  /// Not supported together with extended thinking
+  #[serde(skip_serializing_if = "Option::is_none")]
+  temperature: Option<f32>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  thinking: Option<Thinking>,
   system: Vec<SystemMessage>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  tools: Vec<Tool>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  tool_choice: Option<ToolChoice>,
+}
+
+#[derive(Debug, Serialize)]
+struct Tool {
+  name: String,
+  description: String,
+  input_schema: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct ToolChoice {
+  r#type: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Thinking {
+  r#type: String,
+  /// Part of `max_tokens`
+  budget_tokens: u32,
 }
 
 #[derive(Debug, Serialize)]
@@ -22,6 +57,13 @@ struct Message {
 struct SystemMessage {
   r#type: String,
   text: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  cache_control: Option<CacheControl>,
+}
+
+#[derive(Debug, Serialize)]
+struct CacheControl {
+  r#type: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,29 +76,68 @@ struct AnthropicResponse {
 struct AnthropicUsage {
   input_tokens: u32,
   output_tokens: u32,
+  #[serde(default)]
+  cache_creation_input_tokens: u32,
+  #[serde(default)]
+  cache_read_input_tokens: u32,
 }
 
 #[derive(Debug, Deserialize)]
-struct ContentBlock {
-  text: String,
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+  Text {
+    text: String,
+  },
+  ToolUse {
+    input: Value,
+  },
+  #[serde(other)]
+  Other,
 }
 
+/// API root, overridable with `ANTHROPIC_BASE_URL`
+pub const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com";
+
 pub struct AnthropicClient {
   client: Client,
   api_key: String,
+  /// Messages endpoint
+  url: String,
   model: String,
   max_tokens: u32,
   temperature: f32,
+  /// Extended thinking is only enabled on request, as it is slower and billed as output
+  reasoning_effort: Option<ReasoningEffort>,
+}
+
+/// Claude 3.7 and the Claude 4 families support extended thinking
+fn supports_thinking(model: &str) -> bool {
+  model.starts_with("claude-3-7")
+    || ["claude-opus-", "claude-sonnet-", "claude-haiku-"]
+      .iter()
+      .filter_map(|family| model.strip_prefix(family))
+      .filter_map(|version| version.chars().next()?.to_digit(10))
+      .any(|major| major >= 4)
 }
 
 impl AnthropicClient {
-  pub fn new(api_key: String) -> Self {
+  pub fn new(api_key: String, client: Client) -> Self {
     Self {
-      client: Client::new(),
+      client,
       api_key,
+      url: format!("{ANTHROPIC_BASE_URL}/v1/messages"),
       model: "claude-3-7-sonnet-20250219".to_string(),
       max_tokens: 500,
       temperature: 0.7,
+      reasoning_effort: None,
+    }
+  }
+
+  /// Send requests to another API root, e.g. a proxy or a local stand-in
+  pub fn with_base_url(self, base_url: &str) -> Self {
+    Self {
+      url: format!("{}/v1/messages", base_url.trim_end_matches('/')),
+      ..self
     }
   }
 }
@@ -74,53 +155,119 @@ impl AIClient for AnthropicClient {
     self.temperature = temperature;
   }
 
-  fn generate_response(
+  fn set_reasoning_effort(&mut self, effort: ReasoningEffort) -> bool {
+    if !supports_thinking(&self.model) {
+      return false;
+    }
+
+    self.reasoning_effort = Some(effort);
+    true
+  }
+
+  fn generate_response(&self, system_prompt: String, user_prompt: String) -> ResponseFuture<'_> {
+    Box::pin(self.generate_response_impl(system_prompt, user_prompt, None))
+  }
+
+  fn generate_structured_response(
     &self,
     system_prompt: String,
     user_prompt: String,
-  ) -> std::pin::Pin<
-    Box<dyn std::future::Future<Output = Result<GenerateResponseResult>> + Send + '_>,
-  > {
-    Box::pin(self.generate_response_impl(system_prompt, user_prompt))
+    schema: JsonSchema,
+  ) -> ResponseFuture<'_> {
+    Box::pin(self.generate_response_impl(system_prompt, user_prompt, Some(schema)))
   }
 }
 
+/// Tokens a prompt prefix needs before Anthropic caches it, shorter prefixes are sent uncached
+fn min_cacheable_tokens(model: &str) -> u32 {
+  if model.contains("haiku") { 2048 } else { 1024 }
+}
+
 impl AnthropicClient {
+  /// With a schema, the model is forced to call a tool taking the schema as its input, and the
+  /// tool input is returned as the message. Extended thinking doesn't allow forcing a tool, so
+  /// the system prompt asks for the tool call instead.
   async fn generate_response_impl(
     &self,
     system_prompt: String,
     user_prompt: String,
+    schema: Option<JsonSchema>,
   ) -> Result<GenerateResponseResult> {
-    // Create system and user messages
-    let system_message = SystemMessage {
+    let thinking = self.reasoning_effort.map(|effort| Thinking {
+      r#type: "enabled".to_string(),
+      budget_tokens: effort.budget_tokens(),
+    });
+
+    // The caller's system prompt doesn't change between runs, unlike the instruction added for
+    // thinking, so it goes in its own block. The cached prefix covers the tools and the system
+    // blocks up to the marked one, and is only cached from a minimum length on.
+    let schema_tokens = schema.as_ref().map_or(0, |schema| {
+      estimate_tokens(&schema.description) + estimate_tokens(&schema.schema.to_string())
+    });
+    let cacheable =
+      estimate_tokens(&system_prompt) + schema_tokens >= min_cacheable_tokens(&self.model);
+    let mut system = vec![SystemMessage {
       r#type: "text".to_string(),
       text: system_prompt,
-    };
+      cache_control: cacheable.then(|| CacheControl {
+        r#type: "ephemeral".to_string(),
+      }),
+    }];
+    if let (Some(schema), Some(_)) = (&schema, &thinking) {
+      system.push(SystemMessage {
+        r#type: "text".to_string(),
+        text: format!("Answer only by calling the `{}` tool.", schema.name),
+        cache_control: None,
+      });
+    }
 
     let user_message = Message {
       role: "user".to_string(),
       content: user_prompt,
     };
 
+    let tool_choice = schema.as_ref().map(|schema| match thinking {
+      Some(_) => ToolChoice {
+        r#type: "auto".to_string(),
+        name: None,
+      },
+      None => ToolChoice {
+        r#type: "tool".to_string(),
+        name: Some(schema.name.clone()),
+      },
+    });
+    let tools = schema
+      .map(|schema| Tool {
+        name: schema.name,
+        description: schema.description,
+        input_schema: schema.schema,
+      })
+      .into_iter()
+      .collect();
+
     let request = AnthropicRequest {
       model: self.model.clone(),
-      max_tokens: self.max_tokens,
-      system: vec![system_message],
+      // The thinking budget is part of `max_tokens`, reserve it on top of the answer
+      max_tokens: self.max_tokens + thinking.as_ref().map_or(0, |t| t.budget_tokens),
+      system,
       messages: vec![user_message],
       stream: false,
-      temperature: self.temperature,
+      temperature: thinking.is_none().then_some(self.temperature),
+      thinking,
+      tools,
+      tool_choice,
     };
 
     let response = self
       .client
-      .post("https://api.anthropic.com/v1/messages")
+      .post(&self.url)
       .header("Content-Type", "application/json")
       .header("x-api-key", &self.api_key)
       .header("anthropic-version", "2023-06-01")
       .json(&request)
       .send()
       .await
-      .map_err(|_| ClientError::FailedToSend)?;
+      .map_err(http::send_error)?;
 
     if !response.status().is_success() {
       let error_text = response.text().await.unwrap_or_default();
@@ -132,16 +279,35 @@ impl AnthropicClient {
       .await
       .map_err(|_| ClientError::FailedToParseResponse)?;
 
-    let message = api_response
-      .content
-      .first()
-      .map(|block| block.text.trim().to_string())
-      .unwrap_or_default();
+    // Prefer the tool call, the model may explain itself in text blocks before it
+    let tool_input = api_response.content.iter().find_map(|block| match block {
+      ContentBlock::ToolUse { input } => Some(input.to_string()),
+      _ => None,
+    });
+    let message = tool_input.unwrap_or_else(|| {
+      api_response
+        .content
+        .iter()
+        .filter_map(|block| match block {
+          ContentBlock::Text { text } => Some(text.as_str()),
+          _ => None,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+    });
 
+    let usage = api_response.usage;
     let usage = UsageInfo {
-      input_tokens: api_response.usage.input_tokens,
-      output_tokens: api_response.usage.output_tokens,
-      total_tokens: api_response.usage.input_tokens + api_response.usage.output_tokens,
+      input_tokens: usage.input_tokens,
+      output_tokens: usage.output_tokens,
+      total_tokens: usage.input_tokens
+        + usage.cache_creation_input_tokens
+        + usage.cache_read_input_tokens
+        + usage.output_tokens,
+      cache_creation_input_tokens: usage.cache_creation_input_tokens,
+      cache_read_input_tokens: usage.cache_read_input_tokens,
+      reasoning_tokens: 0,
     };
 
     Ok(GenerateResponseResult { message, usage })
@@ -1,10 +1,12 @@
+use crate::auth;
+use crate::client::ReasoningEffort;
 use clap::{Arg, ArgMatches, Command};
 use std::env;
 
 pub fn get_matches() -> ArgMatches {
   Command::new("gen-commit")
     .version(env!("CARGO_PKG_VERSION"))
-    .about("Generate commit messages using AI models from Anthropic and OpenAI")
+    .about("Generate commit messages using AI models from Anthropic, OpenAI, Google and Azure OpenAI")
     .arg(
       Arg::new("dry-run")
         .short('n')
@@ -17,7 +19,7 @@ pub fn get_matches() -> ArgMatches {
       Arg::new("model")
         .short('m')
         .long("model")
-        .help("Specify the model to use in format 'provider:model' (e.g., anthropic:claude-sonnet-4-20250514 or openai:gpt-4)")
+        .help("Specify the model to use in format 'provider:model' (e.g., anthropic:claude-sonnet-4-20250514, openai:gpt-4, google:gemini-2.5-flash, azure:<deployment> or cmd:<name>)")
         .env("GC_DEFAULT_MODEL")
         .default_value("anthropic:claude-sonnet-4-20250514")
         .value_name("MODEL"),
@@ -26,7 +28,7 @@ pub fn get_matches() -> ArgMatches {
       Arg::new("max-tokens")
         .short('t')
         .long("max-tokens")
-        .help("Maximum number of tokens in the generated response")
+        .help("Maximum number of tokens in the generated response, at most the model's output limit (see `gen-commit models`)")
         .value_name("COUNT")
         .value_parser(clap::value_parser!(u32))
         .default_value("500"),
@@ -48,11 +50,124 @@ pub fn get_matches() -> ArgMatches {
         .help("Enable verbose logging to show current steps and their outputs")
         .action(clap::ArgAction::SetTrue),
     )
+    .arg(
+      Arg::new("signoff")
+        .short('s')
+        .long("signoff")
+        .help("Add a Signed-off-by trailer using the committer identity")
+        .action(clap::ArgAction::SetTrue),
+    )
+    .arg(
+      Arg::new("co-author")
+        .long("co-author")
+        .help("Add a Co-authored-by trailer, either an alias from the config roster or 'Name <email>' (repeatable)")
+        .value_name("AUTHOR")
+        .action(clap::ArgAction::Append),
+    )
+    .arg(
+      Arg::new("trailer")
+        .long("trailer")
+        .help("Add an arbitrary trailer in the form 'key=value' (repeatable)")
+        .value_name("TRAILER")
+        .action(clap::ArgAction::Append),
+    )
+    .arg(
+      Arg::new("generated-by")
+        .long("generated-by")
+        .help("Add a Generated-by trailer recording the model used")
+        .action(clap::ArgAction::SetTrue),
+    )
+    .arg(
+      Arg::new("diff-file")
+        .long("diff-file")
+        .help("Generate a message for the diff in this file, or '-' for stdin, instead of the staged changes (disables committing)")
+        .value_name("PATH"),
+    )
+    .arg(
+      Arg::new("yes")
+        .short('y')
+        .long("yes")
+        .help("Commit without asking for confirmation")
+        .action(clap::ArgAction::SetTrue),
+    )
+    .arg(
+      Arg::new("no-edit")
+        .long("no-edit")
+        .help("Commit with the generated message without opening the editor")
+        .action(clap::ArgAction::SetTrue),
+    )
+    .arg(
+      Arg::new("commit-args")
+        .help("Extra arguments passed through to git commit, e.g. -- -S --no-verify --author='Name <email>'")
+        .value_name("GIT_COMMIT_ARGS")
+        .num_args(0..)
+        .allow_hyphen_values(true)
+        .last(true),
+    )
+    .arg(
+      Arg::new("config")
+        .short('c')
+        .long("config")
+        .help("Path to a config file (defaults to .gen-commit.toml in the repository root, then ~/.config/gen-commit/config.toml)")
+        .value_name("PATH")
+        .env("GC_CONFIG"),
+    )
     .arg(
       Arg::new("no-analysis")
         .long("no-analysis")
         .help("Skip AI-powered change analysis and generate commit message directly from diff")
         .action(clap::ArgAction::SetTrue),
     )
+    .arg(
+      Arg::new("reasoning-effort")
+        .long("reasoning-effort")
+        .help("Let a reasoning model think before answering: OpenAI reasoning effort, or the thinking budget of Claude and Gemini models")
+        .value_name("EFFORT")
+        .value_parser(ReasoningEffort::VALUES),
+    )
+    .arg(
+      Arg::new("no-cache")
+        .long("no-cache")
+        .help("Neither read nor write cached AI responses for this run")
+        .action(clap::ArgAction::SetTrue),
+    )
+    .subcommand(
+      Command::new("cache")
+        .about("Manage the cache of AI responses stored in the git directory")
+        .subcommand_required(true)
+        .subcommand(Command::new("clear").about("Remove all cached responses")),
+    )
+    .subcommand(
+      Command::new("models")
+        .about("List the known models with their limits, supported parameters and prices")
+        .arg(
+          Arg::new("provider")
+            .help("Only list the models of this provider")
+            .value_parser(["anthropic", "openai", "google", "azure", "cmd", "mock"]),
+        ),
+    )
+    .subcommand(
+      Command::new("auth")
+        .about("Manage API keys stored in the system keyring")
+        .subcommand_required(true)
+        .subcommand(
+          Command::new("login")
+            .about("Store an API key, read from stdin, in the system keyring")
+            .arg(provider_arg()),
+        )
+        .subcommand(
+          Command::new("logout")
+            .about("Remove a stored API key from the system keyring")
+            .arg(provider_arg()),
+        )
+        .subcommand(Command::new("status").about("Show where the API key of each provider comes from")),
+    )
     .get_matches()
 }
+
+fn provider_arg() -> Arg {
+  Arg::new("provider")
+    .help("Provider the API key belongs to")
+    .required(true)
+    .value_parser(auth::PROVIDERS)
+}
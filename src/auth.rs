@@ -0,0 +1,287 @@
+use crate::config::ProvidersConfig;
+use std::env;
+use std::fmt;
+use std::io::{BufRead, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// Service name of the keyring entries, the account is the provider name
+const KEYRING_SERVICE: &str = "gen-commit";
+
+/// Providers that authenticate with an API key
+pub const PROVIDERS: [&str; 4] = ["anthropic", "openai", "google", "azure"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+  #[error(
+    "No API key found for {provider}. Set {var}, run `gen-commit auth login {provider}`, or set providers.{provider}.key_command or key_file in the config"
+  )]
+  MissingKey { provider: String, var: String },
+  #[error("Key command `{0}` failed: {1}")]
+  KeyCommandFailed(String, String),
+  #[error("Failed to read key file {0}")]
+  FailedToReadKeyFile(String),
+  #[error("Failed to access the system keyring: {0}")]
+  Keyring(#[from] keyring::Error),
+  #[error("Failed to access the system keyring")]
+  KeyringUnavailable,
+  #[error("Failed to read the API key: {0}")]
+  FailedToReadKey(#[from] std::io::Error),
+  #[error("The API key is empty")]
+  EmptyKey,
+}
+
+type Result<T> = std::result::Result<T, AuthError>;
+
+/// Where an API key was found, in the order they are tried
+#[derive(Debug)]
+pub enum KeySource {
+  Env(String),
+  Command(String),
+  File(String),
+  Config,
+  Keyring,
+}
+
+impl fmt::Display for KeySource {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      KeySource::Env(var) => write!(f, "environment variable {var}"),
+      KeySource::Command(command) => write!(f, "key command `{command}`"),
+      KeySource::File(path) => write!(f, "key file {path}"),
+      KeySource::Config => write!(f, "config file"),
+      KeySource::Keyring => write!(f, "system keyring"),
+    }
+  }
+}
+
+/// Environment variable holding the API key of `provider`
+pub fn key_var(provider: &str) -> &'static str {
+  match provider {
+    "openai" => "OPENAI_API_KEY",
+    "google" => "GEMINI_API_KEY",
+    "azure" => "AZURE_OPENAI_API_KEY",
+    _ => "ANTHROPIC_API_KEY",
+  }
+}
+
+/// API key of `provider` from the environment, the key command or file of the config, or the
+/// keyring
+pub fn get_api_key(provider: &str, providers: &ProvidersConfig) -> Result<String> {
+  find_api_key(provider, providers)?
+    .map(|(key, _)| key)
+    .ok_or_else(|| AuthError::MissingKey {
+      provider: provider.to_string(),
+      var: key_var(provider).to_string(),
+    })
+}
+
+pub fn find_api_key(
+  provider: &str,
+  providers: &ProvidersConfig,
+) -> Result<Option<(String, KeySource)>> {
+  let var = key_var(provider);
+  if let Ok(key) = env::var(var)
+    && !key.trim().is_empty()
+  {
+    return Ok(Some((
+      key.trim().to_string(),
+      KeySource::Env(var.to_string()),
+    )));
+  }
+
+  let (key_command, key_file, config_key) = match provider {
+    "anthropic" => (
+      &providers.anthropic.key_command,
+      &providers.anthropic.key_file,
+      &None,
+    ),
+    "openai" => (
+      &providers.openai.key_command,
+      &providers.openai.key_file,
+      &None,
+    ),
+    "google" => (
+      &providers.google.key_command,
+      &providers.google.key_file,
+      &None,
+    ),
+    _ => (
+      &providers.azure.key_command,
+      &providers.azure.key_file,
+      &providers.azure.api_key,
+    ),
+  };
+
+  if let Some(command) = key_command {
+    let key = run_key_command(command)?;
+    return Ok(Some((key, KeySource::Command(command.clone()))));
+  }
+
+  if let Some(path) = key_file {
+    let key = read_key_file(path)?;
+    return Ok(Some((key, KeySource::File(path.clone()))));
+  }
+
+  if let Some(key) = config_key {
+    return Ok(Some((key.clone(), KeySource::Config)));
+  }
+
+  // Without a secret service, e.g. in containers or CI, the keyring is treated as empty
+  Ok(
+    keyring_get(provider)
+      .ok()
+      .flatten()
+      .map(|key| (key, KeySource::Keyring)),
+  )
+}
+
+/// Run `command` with the shell and use its trimmed stdout, e.g. `pass show openai`
+fn run_key_command(command: &str) -> Result<String> {
+  let (shell, flag) = if cfg!(windows) {
+    ("cmd", "/C")
+  } else {
+    ("sh", "-c")
+  };
+
+  let output = Command::new(shell)
+    .args([flag, command])
+    .stdin(Stdio::inherit())
+    .output()
+    .map_err(|e| AuthError::KeyCommandFailed(command.to_string(), e.to_string()))?;
+
+  if !output.status.success() {
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    return Err(AuthError::KeyCommandFailed(command.to_string(), stderr));
+  }
+
+  // Tools like `pass` print the secret on the first line and metadata below it
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  let key = stdout.lines().next().unwrap_or_default().trim().to_string();
+  if key.is_empty() {
+    return Err(AuthError::KeyCommandFailed(
+      command.to_string(),
+      "it printed nothing".to_string(),
+    ));
+  }
+
+  Ok(key)
+}
+
+fn read_key_file(path: &str) -> Result<String> {
+  let expanded = match path.strip_prefix("~/") {
+    Some(rest) => env::var("HOME")
+      .map(|home| format!("{home}/{rest}"))
+      .unwrap_or_else(|_| path.to_string()),
+    None => path.to_string(),
+  };
+
+  let content = std::fs::read_to_string(&expanded)
+    .map_err(|_| AuthError::FailedToReadKeyFile(path.to_string()))?;
+
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    if let Ok(metadata) = std::fs::metadata(&expanded)
+      && metadata.permissions().mode() & 0o077 != 0
+    {
+      println!(
+        "[OUTPUT] Warning: key file {path} is accessible by other users, run chmod 600 on it"
+      );
+    }
+  }
+
+  let key = content.trim().to_string();
+  if key.is_empty() {
+    return Err(AuthError::FailedToReadKeyFile(path.to_string()));
+  }
+
+  Ok(key)
+}
+
+/// Run `operation` on the keyring entry of `provider`. The blocking Secret Service client
+/// drives its own async runtime, which can't be started on a thread of the tokio runtime.
+fn with_keyring<T: Send>(
+  provider: &str,
+  operation: impl FnOnce(keyring::Entry) -> Result<T> + Send,
+) -> Result<T> {
+  std::thread::scope(|scope| {
+    scope
+      .spawn(|| operation(keyring::Entry::new(KEYRING_SERVICE, provider)?))
+      .join()
+      .unwrap_or(Err(AuthError::KeyringUnavailable))
+  })
+}
+
+/// The stored key, `None` when there is no entry for `provider`
+pub fn keyring_get(provider: &str) -> Result<Option<String>> {
+  with_keyring(provider, |entry| match entry.get_password() {
+    Ok(key) => Ok(Some(key)),
+    Err(keyring::Error::NoEntry) => Ok(None),
+    Err(e) => Err(e.into()),
+  })
+}
+
+pub fn keyring_set(provider: &str, key: &str) -> Result<()> {
+  with_keyring(provider, |entry| Ok(entry.set_password(key)?))
+}
+
+/// Remove the stored key, returning whether there was one
+pub fn keyring_delete(provider: &str) -> Result<bool> {
+  with_keyring(provider, |entry| match entry.delete_credential() {
+    Ok(()) => Ok(true),
+    Err(keyring::Error::NoEntry) => Ok(false),
+    Err(e) => Err(e.into()),
+  })
+}
+
+/// Read a key from stdin, without echoing it when stdin is a terminal
+pub fn read_key(provider: &str) -> Result<String> {
+  let stdin = std::io::stdin();
+  let interactive = stdin.is_terminal();
+
+  if interactive {
+    print!("API key for {provider}: ");
+    std::io::stdout().flush()?;
+  }
+
+  let echo_disabled = interactive && set_echo(false);
+  let mut key = String::new();
+  let result = stdin.lock().read_line(&mut key);
+  if echo_disabled {
+    set_echo(true);
+    println!();
+  }
+  result?;
+
+  let key = key.trim().to_string();
+  if key.is_empty() {
+    return Err(AuthError::EmptyKey);
+  }
+
+  Ok(key)
+}
+
+/// Toggle terminal echo with `stty`, returning whether it succeeded
+fn set_echo(enabled: bool) -> bool {
+  if cfg!(windows) {
+    return false;
+  }
+
+  Command::new("stty")
+    .arg(if enabled { "echo" } else { "-echo" })
+    .stdin(Stdio::inherit())
+    .status()
+    .is_ok_and(|status| status.success())
+}
+
+/// `sk-...1234`, enough to tell keys apart without revealing them
+pub fn mask_key(key: &str) -> String {
+  let chars: Vec<char> = key.chars().collect();
+  if chars.len() <= 12 {
+    return "*".repeat(chars.len());
+  }
+
+  let head: String = chars[..3].iter().collect();
+  let tail: String = chars[chars.len() - 4..].iter().collect();
+  format!("{head}...{tail}")
+}
@@ -0,0 +1,405 @@
+use crate::git;
+use crate::symbols::{FileSymbolChanges, SymbolChange, SymbolChangeKind};
+use regex::Regex;
+
+const BREAKING_CHANGE_FOOTER: &str = "BREAKING CHANGE:";
+
+/// A removal or incompatible change of a public API item
+#[derive(Debug, Clone)]
+pub struct BreakingChange {
+  pub path: String,
+  pub description: String,
+}
+
+/// Find removed, renamed, hidden and re-signed public items in the symbol changes. Rust files
+/// only count when their crate has a library target and their module is reachable from it in
+/// `HEAD` or in the index, as `pub` items of a binary or of a private module are not API.
+pub async fn detect_breaking_changes(files: &[FileSymbolChanges]) -> Vec<BreakingChange> {
+  let head = async |path: &str| git::show_blob(&format!("HEAD:{path}")).await.ok();
+  let index = async |path: &str| git::show_blob(&format!(":{path}")).await.ok();
+  let mut result = Vec::new();
+
+  for file in files {
+    // Removing `pub mod` together with the module's items still breaks the API
+    if !(is_api_path(&file.path, &head).await || is_api_path(&file.path, &index).await) {
+      continue;
+    }
+
+    for change in &file.changes {
+      // Methods disappear together with their type, the type alone is enough
+      if change.kind == SymbolChangeKind::Removed && is_member_of_removed(change, &file.changes) {
+        continue;
+      }
+
+      if let Some(description) = describe(change) {
+        result.push(BreakingChange {
+          path: file.path.clone(),
+          description,
+        });
+      }
+    }
+  }
+
+  result
+}
+
+/// Describe a change that breaks the API. Of the modified items only the visibility and the
+/// signature of functions are compared, so changed fields, variants or type aliases are not
+/// reported.
+fn describe(change: &SymbolChange) -> Option<String> {
+  let symbol = &change.symbol;
+  let previous = change.previous.as_ref();
+
+  match &change.kind {
+    SymbolChangeKind::Added => None,
+    SymbolChangeKind::Removed => symbol
+      .public
+      .then(|| format!("{} `{}` was removed", symbol.kind, symbol.name)),
+    SymbolChangeKind::Renamed { from } => {
+      previous.is_some_and(|previous| previous.public).then(|| {
+        format!(
+          "{} `{}` was renamed to `{}`",
+          symbol.kind, from, symbol.name
+        )
+      })
+    }
+    SymbolChangeKind::Modified => {
+      let previous = previous.filter(|previous| previous.public)?;
+      if !symbol.public {
+        return Some(format!(
+          "{} `{}` is no longer public",
+          symbol.kind, symbol.name
+        ));
+      }
+
+      let is_function = matches!(symbol.kind.as_str(), "fn" | "function" | "method");
+      (is_function && previous.signature != symbol.signature).then(|| {
+        format!(
+          "signature of {} `{}` changed from `{}` to `{}`",
+          symbol.kind, symbol.name, previous.signature, symbol.signature
+        )
+      })
+    }
+  }
+}
+
+/// `Type::method` or `Class.method` whose `Type` or `Class` was removed as well
+fn is_member_of_removed(change: &SymbolChange, changes: &[SymbolChange]) -> bool {
+  let name = &change.symbol.name;
+  let Some((parent, _)) = name.rsplit_once("::").or_else(|| name.rsplit_once('.')) else {
+    return false;
+  };
+
+  changes
+    .iter()
+    .any(|other| other.kind == SymbolChangeKind::Removed && other.symbol.name == parent)
+}
+
+/// Tests and examples are never API; Rust files need a `src/lib.rs` in their crate that
+/// exposes their module. Files are read with `read`, from a git revision.
+async fn is_api_path(path: &str, read: &impl AsyncFn(&str) -> Option<String>) -> bool {
+  let segments: Vec<&str> = path.split('/').collect();
+  let in_non_api_dir = segments[..segments.len() - 1].iter().any(|segment| {
+    matches!(
+      *segment,
+      "tests" | "test" | "__tests__" | "examples" | "benches"
+    )
+  });
+  let filename = segments.last().copied().unwrap_or_default();
+  if in_non_api_dir || filename.contains(".test.") || filename.contains(".spec.") {
+    return false;
+  }
+
+  if !path.ends_with(".rs") {
+    return true;
+  }
+
+  // The nearest directory with a Cargo.toml is the crate root
+  for depth in (0..segments.len()).rev() {
+    let dir = segments[..depth].join("/");
+    if read(&join_path(&dir, "Cargo.toml")).await.is_some() {
+      return is_reachable_module(&dir, path, read).await;
+    }
+  }
+
+  false
+}
+
+/// Whether the module of a Rust file can be named from outside its crate: `lib.rs` itself, or
+/// every module on the way down from it is declared `pub mod` or glob re-exported by its parent
+async fn is_reachable_module(
+  crate_dir: &str,
+  path: &str,
+  read: &impl AsyncFn(&str) -> Option<String>,
+) -> bool {
+  let src_dir = join_path(crate_dir, "src");
+  let Some(relative) = path
+    .strip_prefix(&format!("{src_dir}/"))
+    .and_then(|relative| relative.strip_suffix(".rs"))
+  else {
+    return false;
+  };
+
+  let mut modules: Vec<&str> = relative.split('/').collect();
+  if modules.last() == Some(&"mod") {
+    modules.pop();
+  }
+  if matches!(modules[0], "main" | "bin") {
+    return false;
+  }
+  let Some(lib) = read(&format!("{src_dir}/lib.rs")).await else {
+    return false;
+  };
+  if modules == ["lib"] {
+    return true;
+  }
+
+  for depth in 0..modules.len() {
+    let source = if depth == 0 {
+      Some(lib.clone())
+    } else {
+      let dir = modules[..depth].join("/");
+      match read(&format!("{src_dir}/{dir}.rs")).await {
+        Some(source) => Some(source),
+        None => read(&format!("{src_dir}/{dir}/mod.rs")).await,
+      }
+    };
+
+    if !source.is_some_and(|source| exposes_module(&source, modules[depth])) {
+      return false;
+    }
+  }
+
+  true
+}
+
+/// `pub mod name`, `pub use name::*` or `pub use name`. Re-exporting single items like
+/// `pub use name::Item` keeps the rest of the module private, so it doesn't count.
+fn exposes_module(source: &str, name: &str) -> bool {
+  let name = regex::escape(name);
+  Regex::new(&format!(
+    r"(?m)^\s*pub\s+mod\s+{name}\b|^\s*pub\s+use\s+(?:self::)?{name}(?:::\*)?\s*;"
+  ))
+  .is_ok_and(|regex| regex.is_match(source))
+}
+
+fn join_path(dir: &str, file: &str) -> String {
+  if dir.is_empty() {
+    file.to_string()
+  } else {
+    format!("{dir}/{file}")
+  }
+}
+
+/// Draft of the `BREAKING CHANGE` footer, one sentence per change
+pub fn footer_draft(changes: &[BreakingChange]) -> Option<String> {
+  if changes.is_empty() {
+    return None;
+  }
+
+  let descriptions: Vec<String> = changes
+    .iter()
+    .map(|change| format!("{} in {}", change.description, change.path))
+    .collect();
+
+  Some(format!(
+    "{} {}",
+    BREAKING_CHANGE_FOOTER,
+    descriptions.join("; ")
+  ))
+}
+
+pub fn format_breaking_changes(changes: &[BreakingChange]) -> String {
+  let Some(footer) = footer_draft(changes) else {
+    return "None".to_string();
+  };
+
+  let mut result = String::new();
+  for change in changes {
+    result.push_str(&format!("- {}: {}\n", change.path, change.description));
+  }
+  result.push_str(&format!("\nFooter draft:\n{footer}\n"));
+
+  result
+}
+
+/// Mark the subject with `!` and add the footer draft unless the message already describes a
+/// breaking change
+pub fn apply_breaking_changes(message: &str, changes: &[BreakingChange]) -> String {
+  let Some(footer) = footer_draft(changes) else {
+    return message.to_string();
+  };
+
+  let (subject, rest) = message.split_once('\n').unwrap_or((message, ""));
+  // `type(scope):` becomes `type(scope)!:`, after an optional `[KEY] ` prefix
+  let subject = match Regex::new(r"^((?:\[[^\]]*\]\s*)?[a-zA-Z]+(?:\([^)]*\))?)!?:") {
+    Ok(regex) => regex.replace(subject, "$1!:").to_string(),
+    Err(_) => subject.to_string(),
+  };
+
+  let has_footer = rest
+    .lines()
+    .any(|line| line.starts_with(BREAKING_CHANGE_FOOTER) || line.starts_with("BREAKING-CHANGE:"));
+  let rest = rest.trim_end();
+
+  match (has_footer, rest.is_empty()) {
+    (true, _) => format!("{subject}\n{rest}"),
+    (false, true) => format!("{subject}\n\n{footer}"),
+    (false, false) => format!("{subject}\n{rest}\n\n{footer}"),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::symbols::Symbol;
+  use std::collections::HashMap;
+
+  fn symbol(kind: &str, name: &str, signature: &str, public: bool) -> Symbol {
+    Symbol {
+      kind: kind.to_string(),
+      name: name.to_string(),
+      key: name.to_string(),
+      name_range: None,
+      text: signature.to_string(),
+      signature: signature.to_string(),
+      public,
+    }
+  }
+
+  fn change(kind: SymbolChangeKind, symbol: Symbol, previous: Option<Symbol>) -> SymbolChange {
+    SymbolChange {
+      kind,
+      symbol,
+      previous,
+    }
+  }
+
+  /// Whether `path` is API in a revision with the given files
+  async fn is_api_in(files: &[(&str, &str)], path: &str) -> bool {
+    let files: HashMap<&str, &str> = files.iter().copied().collect();
+    let read = async |path: &str| files.get(path).map(|content| content.to_string());
+    is_api_path(path, &read).await
+  }
+
+  #[test]
+  fn describes_breaking_symbol_changes() {
+    let public_fn = symbol("fn", "run", "pub fn run()", true);
+    let cases = [
+      (
+        change(SymbolChangeKind::Removed, public_fn.clone(), None),
+        Some("fn `run` was removed"),
+      ),
+      (
+        change(
+          SymbolChangeKind::Removed,
+          symbol("fn", "helper", "fn helper()", false),
+          None,
+        ),
+        None,
+      ),
+      (
+        change(
+          SymbolChangeKind::Modified,
+          symbol("fn", "run", "pub fn run(fast: bool)", true),
+          Some(public_fn.clone()),
+        ),
+        Some("signature of fn `run` changed from `pub fn run()` to `pub fn run(fast: bool)`"),
+      ),
+      (
+        change(
+          SymbolChangeKind::Modified,
+          symbol("fn", "run", "pub(crate) fn run()", false),
+          Some(public_fn.clone()),
+        ),
+        Some("fn `run` is no longer public"),
+      ),
+      (
+        change(
+          SymbolChangeKind::Renamed {
+            from: "run".to_string(),
+          },
+          symbol("fn", "start", "pub fn start()", true),
+          Some(public_fn.clone()),
+        ),
+        Some("fn `run` was renamed to `start`"),
+      ),
+      (
+        change(
+          SymbolChangeKind::Added,
+          symbol("fn", "stop", "pub fn stop()", true),
+          None,
+        ),
+        None,
+      ),
+    ];
+
+    for (change, expected) in cases {
+      assert_eq!(describe(&change).as_deref(), expected, "{change:?}");
+    }
+  }
+
+  #[tokio::test]
+  async fn only_modules_reachable_from_lib_rs_are_api() {
+    let files = [
+      ("Cargo.toml", "[package]\nname = \"demo\"\n"),
+      (
+        "src/lib.rs",
+        "pub mod api;\nmod internal;\nmod glob;\npub use glob::*;\nmod single;\npub use single::Client;\n",
+      ),
+      (
+        "src/api/mod.rs",
+        "pub mod v1;\nmod detail;\npub(crate) mod shared;\n",
+      ),
+      ("src/main.rs", "fn main() {}\n"),
+    ];
+
+    let cases = [
+      ("src/lib.rs", true),
+      ("src/api/mod.rs", true),
+      ("src/api/v1.rs", true),
+      ("src/glob.rs", true),
+      ("src/single.rs", false),
+      ("src/internal.rs", false),
+      ("src/api/detail.rs", false),
+      ("src/api/shared.rs", false),
+      ("src/main.rs", false),
+      ("tests/api.rs", false),
+    ];
+    for (path, expected) in cases {
+      assert_eq!(is_api_in(&files, path).await, expected, "{path}");
+    }
+  }
+
+  #[tokio::test]
+  async fn binary_crates_have_no_api() {
+    let files = [
+      ("crates/cli/Cargo.toml", "[package]\nname = \"cli\"\n"),
+      ("crates/cli/src/main.rs", "pub mod args;\n"),
+    ];
+
+    assert!(!is_api_in(&files, "crates/cli/src/args.rs").await);
+  }
+
+  #[test]
+  fn marks_the_subject_and_adds_the_footer() {
+    let changes = [BreakingChange {
+      path: "src/lib.rs".to_string(),
+      description: "fn `run` was removed".to_string(),
+    }];
+
+    assert_eq!(
+      apply_breaking_changes("feat(api): drop run\n\n- Remove run", &changes),
+      "feat(api)!: drop run\n\n- Remove run\n\nBREAKING CHANGE: fn `run` was removed in src/lib.rs"
+    );
+    assert_eq!(
+      apply_breaking_changes("[PROJ-1] fix: drop run", &changes),
+      "[PROJ-1] fix!: drop run\n\nBREAKING CHANGE: fn `run` was removed in src/lib.rs"
+    );
+    assert_eq!(
+      apply_breaking_changes("feat!: drop run\n\nBREAKING CHANGE: run is gone", &changes),
+      "feat!: drop run\n\nBREAKING CHANGE: run is gone"
+    );
+    assert_eq!(apply_breaking_changes("feat: add", &[]), "feat: add");
+  }
+}
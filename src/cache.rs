@@ -0,0 +1,157 @@
+use crate::client::{GenerateResponseResult, UsageInfo};
+use crate::config::CacheConfig;
+use crate::git;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Cache directory inside the git directory, so it is never committed
+const CACHE_DIR: &str = "gen-commit/cache";
+
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+  #[error("Failed to locate the cache directory")]
+  NotFound,
+  #[error("Failed to clear cache directory {0}")]
+  FailedToClear(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+  /// Seconds since the Unix epoch
+  created_at: u64,
+  message: String,
+  usage: UsageInfo,
+}
+
+/// Responses stored on disk by a hash of everything that went into the request
+pub struct ResponseCache {
+  dir: PathBuf,
+  ttl: Duration,
+  max_size: u64,
+}
+
+impl ResponseCache {
+  pub async fn open(config: &CacheConfig) -> Result<Self, CacheError> {
+    let git_dir = git::get_git_dir().await.map_err(|_| CacheError::NotFound)?;
+
+    Ok(Self {
+      dir: PathBuf::from(git_dir).join(CACHE_DIR),
+      ttl: Duration::from_secs(config.ttl_hours * 60 * 60),
+      max_size: config.max_size_kb * 1024,
+    })
+  }
+
+  /// SHA-256 of the parts, e.g. the model and the prompts
+  pub fn key(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+      hasher.update(part.as_bytes());
+      // Separate the parts so `ab` + `c` and `a` + `bc` hash differently
+      hasher.update([0]);
+    }
+
+    format!("{:x}", hasher.finalize())
+  }
+
+  fn entry_path(&self, key: &str) -> PathBuf {
+    self.dir.join(format!("{key}.json"))
+  }
+
+  /// A cached response that has not expired. Its usage is zero, as nothing was billed.
+  pub async fn get(&self, key: &str) -> Option<GenerateResponseResult> {
+    let content = tokio::fs::read_to_string(self.entry_path(key)).await.ok()?;
+    let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+
+    if now().saturating_sub(entry.created_at) > self.ttl.as_secs() {
+      return None;
+    }
+
+    Some(GenerateResponseResult {
+      message: entry.message,
+      usage: UsageInfo::default(),
+    })
+  }
+
+  /// Store a response, then prune the cache. Failures are ignored, the cache is best effort.
+  pub async fn put(&self, key: &str, response: &GenerateResponseResult) {
+    let entry = CacheEntry {
+      created_at: now(),
+      message: response.message.clone(),
+      usage: response.usage.clone(),
+    };
+    let Ok(content) = serde_json::to_string(&entry) else {
+      return;
+    };
+
+    if tokio::fs::create_dir_all(&self.dir).await.is_err() {
+      return;
+    }
+    let _ = tokio::fs::write(self.entry_path(key), content).await;
+
+    self.prune().await;
+  }
+
+  /// Remove expired entries, then the oldest ones until the cache fits its size limit
+  async fn prune(&self) {
+    let mut entries = self.entries().await;
+    entries.sort_by_key(|(_, modified, _)| *modified);
+
+    let expired_before = SystemTime::now()
+      .checked_sub(self.ttl)
+      .unwrap_or(UNIX_EPOCH);
+    let mut total_size: u64 = entries.iter().map(|(_, _, size)| size).sum();
+
+    for (path, modified, size) in entries {
+      if modified >= expired_before && total_size <= self.max_size {
+        continue;
+      }
+      if tokio::fs::remove_file(&path).await.is_ok() {
+        total_size = total_size.saturating_sub(size);
+      }
+    }
+  }
+
+  /// Cache files with their modification time and size
+  async fn entries(&self) -> Vec<(PathBuf, SystemTime, u64)> {
+    let mut entries = Vec::new();
+    let Ok(mut dir) = tokio::fs::read_dir(&self.dir).await else {
+      return entries;
+    };
+
+    while let Ok(Some(entry)) = dir.next_entry().await {
+      let path = entry.path();
+      if path.extension().is_none_or(|ext| ext != "json") {
+        continue;
+      }
+      if let Ok(metadata) = entry.metadata().await {
+        let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+        entries.push((path, modified, metadata.len()));
+      }
+    }
+
+    entries
+  }
+
+  /// Remove every cache entry, returning how many were removed
+  pub async fn clear(&self) -> Result<usize, CacheError> {
+    let entries = self.entries().await;
+    let count = entries.len();
+
+    for (path, _, _) in entries {
+      tokio::fs::remove_file(&path)
+        .await
+        .map_err(|_| CacheError::FailedToClear(self.dir.display().to_string()))?;
+    }
+
+    Ok(count)
+  }
+}
+
+fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or_default()
+}
@@ -0,0 +1,434 @@
+use crate::config::{ClassifyConfig, ClassifyRule};
+use crate::diff::Diff;
+use globset::{GlobBuilder, GlobMatcher};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClassifyError {
+  #[error("Invalid classification pattern {0}")]
+  InvalidPattern(String),
+}
+
+/// Category for files with a detected programming language and no matching rule
+const SOURCE_CATEGORY: &str = "Source";
+/// Category for everything else
+const OTHER_CATEGORY: &str = "Other";
+
+/// Built-in rules, checked after the user rules. Earlier rules take precedence, so tests are
+/// recognized before their language makes them source files.
+const DEFAULT_RULES: &[(&str, &[&str])] = &[
+  (
+    "Tests",
+    &[
+      "**/tests/**",
+      "**/test/**",
+      "**/__tests__/**",
+      "**/*.test.*",
+      "**/*.spec.*",
+      "**/*_test.go",
+      "**/test_*.py",
+      "**/*_test.py",
+    ],
+  ),
+  (
+    "CI",
+    &[
+      ".github/workflows/**",
+      ".gitlab-ci.yml",
+      ".circleci/**",
+      "**/Jenkinsfile",
+      "azure-pipelines.yml",
+    ],
+  ),
+  (
+    "Build",
+    &[
+      "**/Cargo.toml",
+      "**/Cargo.lock",
+      "**/build.rs",
+      "**/package.json",
+      "**/package-lock.json",
+      "**/pnpm-lock.yaml",
+      "**/yarn.lock",
+      "**/bun.lock",
+      "**/go.mod",
+      "**/go.sum",
+      "**/pyproject.toml",
+      "**/requirements*.txt",
+      "**/Makefile",
+      "**/Dockerfile",
+      "**/*.gradle",
+      "**/pom.xml",
+    ],
+  ),
+  (
+    "Docs",
+    &[
+      "**/docs/**",
+      "**/*.md",
+      "**/*.mdx",
+      "**/*.rst",
+      "**/README*",
+      "**/LICENSE*",
+      "**/CHANGELOG*",
+    ],
+  ),
+  (
+    "Styles",
+    &["**/*.css", "**/*.scss", "**/*.sass", "**/*.less"],
+  ),
+  (
+    "Config",
+    &[
+      "**/*.toml",
+      "**/*.json",
+      "**/*.yaml",
+      "**/*.yml",
+      "**/*.ini",
+      "**/*.config.*",
+      "**/.*rc",
+      "**/.env*",
+      "**/.gitignore",
+    ],
+  ),
+];
+
+/// Languages by file extension, in the spirit of GitHub linguist
+const LANGUAGES_BY_EXTENSION: &[(&str, &str)] = &[
+  ("rs", "Rust"),
+  ("go", "Go"),
+  ("py", "Python"),
+  ("rb", "Ruby"),
+  ("php", "PHP"),
+  ("java", "Java"),
+  ("kt", "Kotlin"),
+  ("kts", "Kotlin"),
+  ("scala", "Scala"),
+  ("swift", "Swift"),
+  ("c", "C"),
+  ("h", "C"),
+  ("cc", "C++"),
+  ("cpp", "C++"),
+  ("cxx", "C++"),
+  ("hpp", "C++"),
+  ("cs", "C#"),
+  ("fs", "F#"),
+  ("js", "JavaScript"),
+  ("mjs", "JavaScript"),
+  ("cjs", "JavaScript"),
+  ("jsx", "JavaScript"),
+  ("ts", "TypeScript"),
+  ("mts", "TypeScript"),
+  ("cts", "TypeScript"),
+  ("tsx", "TypeScript"),
+  ("vue", "Vue"),
+  ("svelte", "Svelte"),
+  ("html", "HTML"),
+  ("css", "CSS"),
+  ("scss", "SCSS"),
+  ("sass", "Sass"),
+  ("less", "Less"),
+  ("sh", "Shell"),
+  ("bash", "Shell"),
+  ("zsh", "Shell"),
+  ("fish", "Fish"),
+  ("lua", "Lua"),
+  ("ex", "Elixir"),
+  ("exs", "Elixir"),
+  ("erl", "Erlang"),
+  ("hs", "Haskell"),
+  ("ml", "OCaml"),
+  ("clj", "Clojure"),
+  ("dart", "Dart"),
+  ("zig", "Zig"),
+  ("nix", "Nix"),
+  ("sql", "SQL"),
+  ("proto", "Protocol Buffers"),
+  ("md", "Markdown"),
+  ("toml", "TOML"),
+  ("json", "JSON"),
+  ("yaml", "YAML"),
+  ("yml", "YAML"),
+];
+
+/// Languages by exact file name, for files without a meaningful extension
+const LANGUAGES_BY_FILENAME: &[(&str, &str)] = &[
+  ("Dockerfile", "Dockerfile"),
+  ("Makefile", "Makefile"),
+  ("Jenkinsfile", "Groovy"),
+  ("Rakefile", "Ruby"),
+  ("Gemfile", "Ruby"),
+];
+
+/// Languages that are markup or data rather than code, never classified as source
+const DATA_LANGUAGES: &[&str] = &["Markdown", "TOML", "JSON", "YAML", "HTML"];
+
+struct Rule {
+  category: String,
+  matcher: GlobMatcher,
+}
+
+pub struct Classifier {
+  rules: Vec<Rule>,
+  /// User extension overrides, checked before the built-in table
+  languages: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassifiedFile {
+  pub path: String,
+  pub language: Option<String>,
+  pub added: usize,
+  pub removed: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Category {
+  pub name: String,
+  pub files: Vec<ClassifiedFile>,
+  pub added: usize,
+  pub removed: usize,
+}
+
+impl Classifier {
+  pub fn new(config: &ClassifyConfig) -> Result<Self, ClassifyError> {
+    let user_rules = config.rules.iter().cloned();
+    let default_rules = DEFAULT_RULES.iter().flat_map(|(category, patterns)| {
+      patterns.iter().map(|pattern| ClassifyRule {
+        pattern: pattern.to_string(),
+        category: category.to_string(),
+      })
+    });
+
+    let rules = user_rules
+      .chain(default_rules)
+      .map(|rule| {
+        let matcher = GlobBuilder::new(&rule.pattern)
+          .literal_separator(true)
+          .build()
+          .map_err(|_| ClassifyError::InvalidPattern(rule.pattern.clone()))?
+          .compile_matcher();
+        Ok(Rule {
+          category: rule.category,
+          matcher,
+        })
+      })
+      .collect::<Result<Vec<Rule>, ClassifyError>>()?;
+
+    let mut languages: Vec<(String, String)> = config
+      .languages
+      .iter()
+      .map(|(ext, language)| (ext.trim_start_matches('.').to_string(), language.clone()))
+      .collect();
+    languages.sort();
+
+    Ok(Self { rules, languages })
+  }
+
+  pub fn language_of(&self, path: &str) -> Option<String> {
+    let filename = path.rsplit('/').next().unwrap_or(path);
+    let extension = filename.rsplit_once('.').map(|(_, ext)| ext);
+
+    if let Some(extension) = extension {
+      let user = self.languages.iter().find(|(ext, _)| ext == extension);
+      if let Some((_, language)) = user {
+        return Some(language.clone());
+      }
+      let builtin = LANGUAGES_BY_EXTENSION
+        .iter()
+        .find(|(ext, _)| *ext == extension);
+      if let Some((_, language)) = builtin {
+        return Some(language.to_string());
+      }
+    }
+
+    LANGUAGES_BY_FILENAME
+      .iter()
+      .find(|(name, _)| *name == filename)
+      .map(|(_, language)| language.to_string())
+  }
+
+  /// Category of a file: the first matching rule, then `Source` for code, then `Other`
+  pub fn category_of(&self, path: &str, language: Option<&str>) -> String {
+    if let Some(rule) = self.rules.iter().find(|rule| rule.matcher.is_match(path)) {
+      return rule.category.clone();
+    }
+
+    match language {
+      Some(language) if !DATA_LANGUAGES.contains(&language) => SOURCE_CATEGORY.to_string(),
+      _ => OTHER_CATEGORY.to_string(),
+    }
+  }
+
+  /// Group the files of the diff by category. Categories are ordered by rule precedence, with
+  /// `Source` and `Other` last; files keep their order in the diff.
+  pub fn classify(&self, diff: &Diff) -> Vec<Category> {
+    let mut order: Vec<&str> = Vec::new();
+    for rule in &self.rules {
+      if !order.contains(&rule.category.as_str()) {
+        order.push(&rule.category);
+      }
+    }
+    order.extend([SOURCE_CATEGORY, OTHER_CATEGORY]);
+
+    let mut categories: Vec<Category> = order
+      .iter()
+      .map(|name| Category {
+        name: name.to_string(),
+        files: Vec::new(),
+        added: 0,
+        removed: 0,
+      })
+      .collect();
+
+    for file in &diff.files {
+      let language = self.language_of(&file.path);
+      let name = self.category_of(&file.path, language.as_deref());
+      let Some(category) = categories.iter_mut().find(|c| c.name == name) else {
+        continue;
+      };
+
+      category.added += file.added;
+      category.removed += file.removed;
+      category.files.push(ClassifiedFile {
+        path: file.path.clone(),
+        language,
+        added: file.added,
+        removed: file.removed,
+      });
+    }
+
+    categories.retain(|category| !category.files.is_empty());
+    categories
+  }
+}
+
+/// Format the categories for the prompt, e.g. `- Source (+12 -3): src/main.rs (Rust)`
+pub fn format_categories(categories: &[Category]) -> String {
+  let mut result = String::new();
+  for category in categories {
+    let files: Vec<String> = category
+      .files
+      .iter()
+      .map(|file| match &file.language {
+        Some(language) => format!("{} ({})", file.path, language),
+        None => file.path.clone(),
+      })
+      .collect();
+    result.push_str(&format!(
+      "- {} (+{} -{}): {}\n",
+      category.name,
+      category.added,
+      category.removed,
+      files.join(", ")
+    ));
+  }
+
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A diff adding one line to every path
+  fn diff_of(paths: &[&str]) -> Diff {
+    let raw: String = paths
+      .iter()
+      .map(|path| {
+        format!("diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n@@ -1,0 +1 @@\n+x\n")
+      })
+      .collect();
+    Diff::parse(&raw)
+  }
+
+  fn categories(classifier: &Classifier, paths: &[&str]) -> Vec<String> {
+    classifier
+      .classify(&diff_of(paths))
+      .into_iter()
+      .map(|category| category.name)
+      .collect()
+  }
+
+  #[test]
+  fn path_sets_get_their_category() {
+    let classifier = Classifier::new(&ClassifyConfig::default()).unwrap();
+    let cases: &[(&[&str], &str)] = &[
+      (&["README.md", "docs/guide/setup.rst", "LICENSE"], "Docs"),
+      (
+        &["tests/api.rs", "src/foo.test.rs", "pkg/api_test.go"],
+        "Tests",
+      ),
+      (&["src/__tests__/app.spec.ts", "test_utils.py"], "Tests"),
+      (&[".github/workflows/ci.yml", ".gitlab-ci.yml"], "CI"),
+      (&["build.rs", "Makefile", "crates/core/Dockerfile"], "Build"),
+      (&["Cargo.toml", "Cargo.lock", "web/package.json"], "Build"),
+      (
+        &["pnpm-lock.yaml", "go.sum", "requirements-dev.txt"],
+        "Build",
+      ),
+      (&["src/app.js", "src/main.rs", "cmd/server.go"], "Source"),
+      (&["styles/main.scss"], "Styles"),
+      (&["tsconfig.json", ".eslintrc", "vite.config.ts"], "Config"),
+      (&["assets/logo.png", "data/notes.txt"], "Other"),
+    ];
+
+    for (paths, expected) in cases {
+      assert_eq!(&categories(&classifier, paths), &[*expected], "{paths:?}");
+    }
+  }
+
+  #[test]
+  fn categories_follow_rule_precedence_with_line_counts() {
+    let classifier = Classifier::new(&ClassifyConfig::default()).unwrap();
+    let classified = classifier.classify(&diff_of(&[
+      "src/lib.rs",
+      "README.md",
+      "tests/cli.rs",
+      "src/util.rs",
+      "notes.txt",
+    ]));
+
+    let names: Vec<&str> = classified.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["Tests", "Docs", "Source", "Other"]);
+    let source = &classified[2];
+    assert_eq!(source.added, 2);
+    assert_eq!(source.files[0].path, "src/lib.rs");
+    assert_eq!(source.files[1].language.as_deref(), Some("Rust"));
+  }
+
+  #[test]
+  fn user_rules_and_languages_come_first() {
+    let config = ClassifyConfig {
+      rules: vec![ClassifyRule {
+        pattern: "apps/web/**".to_string(),
+        category: "Frontend".to_string(),
+      }],
+      languages: [("mdx".to_string(), "MDX".to_string())].into(),
+    };
+    let classifier = Classifier::new(&config).unwrap();
+
+    assert_eq!(
+      categories(
+        &classifier,
+        &["apps/web/app.test.ts", "apps/web/package.json"]
+      ),
+      ["Frontend"]
+    );
+    assert_eq!(
+      classifier.language_of("docs/intro.mdx").as_deref(),
+      Some("MDX")
+    );
+
+    let invalid = ClassifyConfig {
+      rules: vec![ClassifyRule {
+        pattern: "src/[".to_string(),
+        category: "Broken".to_string(),
+      }],
+      ..ClassifyConfig::default()
+    };
+    assert!(matches!(
+      Classifier::new(&invalid),
+      Err(ClassifyError::InvalidPattern(_))
+    ));
+  }
+}
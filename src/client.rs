@@ -1,13 +1,31 @@
 use crate::anthropic::AnthropicClient;
-use crate::openai::OpenAIClient;
+use crate::auth::{self, AuthError};
+use crate::command::CommandClient;
+use crate::config::{CommandConfig, NetworkConfig, ProviderConfig, ProvidersConfig};
+use crate::gemini::GeminiClient;
+use crate::http::{self, HttpError};
+use crate::message::JsonSchema;
+use crate::mock::MockClient;
+use crate::models::{ModelError, ModelRegistry};
+use crate::openai::{AZURE_DEFAULT_API_VERSION, OpenAIClient};
+use serde::{Deserialize, Serialize};
 use std::env;
-use std::env::VarError;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UsageInfo {
+  /// Input tokens billed at the regular rate, excluding cache writes and reads
   pub input_tokens: u32,
   pub output_tokens: u32,
   pub total_tokens: u32,
+  /// Input tokens written to the provider's prompt cache
+  #[serde(default)]
+  pub cache_creation_input_tokens: u32,
+  /// Input tokens read from the provider's prompt cache
+  #[serde(default)]
+  pub cache_read_input_tokens: u32,
+  /// Part of `output_tokens` spent on reasoning before the answer
+  #[serde(default)]
+  pub reasoning_tokens: u32,
 }
 
 #[derive(Debug)]
@@ -18,32 +36,98 @@ pub struct GenerateResponseResult {
 
 #[derive(thiserror::Error, Debug)]
 pub enum ClientError {
-  #[error("Failed to send request to API")]
-  FailedToSend,
+  #[error("Failed to send request to API: {0}")]
+  FailedToSend(String),
   #[error("API request failed: {0}")]
   RequestFailed(String),
   #[error("Failed to parse API response")]
   FailedToParseResponse,
 }
 
+/// How much a reasoning model thinks before answering
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReasoningEffort {
+  Minimal,
+  Low,
+  Medium,
+  High,
+}
+
+impl ReasoningEffort {
+  pub const VALUES: [&str; 4] = ["minimal", "low", "medium", "high"];
+
+  pub fn parse(value: &str) -> Option<Self> {
+    match value {
+      "minimal" => Some(Self::Minimal),
+      "low" => Some(Self::Low),
+      "medium" => Some(Self::Medium),
+      "high" => Some(Self::High),
+      _ => None,
+    }
+  }
+
+  /// Value of OpenAI's `reasoning.effort`
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Minimal => "minimal",
+      Self::Low => "low",
+      Self::Medium => "medium",
+      Self::High => "high",
+    }
+  }
+
+  /// Tokens reserved for reasoning on top of the answer, also used as the thinking budget of
+  /// Anthropic and Gemini models
+  pub fn budget_tokens(&self) -> u32 {
+    match self {
+      Self::Minimal => 1024,
+      Self::Low => 2048,
+      Self::Medium => 8192,
+      Self::High => 16384,
+    }
+  }
+}
+
+pub type ResponseFuture<'a> =
+  std::pin::Pin<Box<dyn std::future::Future<Output = Result<GenerateResponseResult>> + Send + 'a>>;
+
 pub type Result<T> = std::result::Result<T, ClientError>;
 
 pub trait AIClient: Send + Sync {
   fn set_model(&mut self, model: String);
   fn set_max_tokens(&mut self, max_tokens: u32);
   fn set_temperature(&mut self, temperature: f32);
-  fn generate_response(
+  /// Enable reasoning with the given effort, returning whether the model supports it. Called
+  /// after `set_model`; providers without reasoning models ignore it.
+  fn set_reasoning_effort(&mut self, _effort: ReasoningEffort) -> bool {
+    false
+  }
+  /// The system prompt is the static prefix shared by every request and may be cached by the
+  /// provider; the user prompt is the dynamic suffix with the changes.
+  fn generate_response(&self, system_prompt: String, user_prompt: String) -> ResponseFuture<'_>;
+  /// Generate a response whose message is a JSON object matching `schema`. Providers without
+  /// native structured output get the schema appended to the system prompt.
+  fn generate_structured_response(
     &self,
     system_prompt: String,
     user_prompt: String,
-  ) -> std::pin::Pin<
-    Box<dyn std::future::Future<Output = Result<GenerateResponseResult>> + Send + '_>,
-  >;
+    schema: JsonSchema,
+  ) -> ResponseFuture<'_> {
+    let system_prompt = format!(
+      "{system_prompt}\n\nRespond only with a JSON object matching this JSON schema, without code fences or explanations:\n{}",
+      schema.schema
+    );
+    self.generate_response(system_prompt, user_prompt)
+  }
 }
 
 pub enum ModelProvider {
   OpenAI,
   Anthropic,
+  Google,
+  Azure,
+  Command,
+  Mock,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -57,13 +141,27 @@ pub enum CreateClientError {
   #[error("Failed to parse model")]
   ParseError(#[from] ParseModelError),
   #[error(transparent)]
-  APIKeyError(#[from] VarError),
+  APIKeyError(#[from] AuthError),
+  #[error("{0} is not set")]
+  MissingSetting(String),
+  #[error(transparent)]
+  Http(#[from] HttpError),
+  #[error(
+    "{0} does not support --reasoning-effort, use a reasoning model like openai:o4-mini, openai:gpt-5 or anthropic:claude-sonnet-4-20250514"
+  )]
+  ReasoningNotSupported(String),
+  #[error(transparent)]
+  Model(#[from] ModelError),
 }
 
 pub struct ClientBuilder {
   model: String,
   max_tokens: Option<u32>,
   temperature: Option<f32>,
+  reasoning_effort: Option<ReasoningEffort>,
+  providers: ProvidersConfig,
+  network: NetworkConfig,
+  models: ModelRegistry,
 }
 
 impl ClientBuilder {
@@ -72,9 +170,31 @@ impl ClientBuilder {
       model: model.to_string(),
       max_tokens: None,
       temperature: None,
+      reasoning_effort: None,
+      providers: ProvidersConfig::default(),
+      network: NetworkConfig::default(),
+      models: ModelRegistry::default(),
     }
   }
 
+  /// Provider settings from the config file, e.g. the Azure resource
+  pub fn providers(mut self, providers: ProvidersConfig) -> Self {
+    self.providers = providers;
+    self
+  }
+
+  /// Proxy, TLS and timeout settings of the HTTP client
+  pub fn network(mut self, network: NetworkConfig) -> Self {
+    self.network = network;
+    self
+  }
+
+  /// Catalog the options are validated against, models missing from it are not checked
+  pub fn models(mut self, models: ModelRegistry) -> Self {
+    self.models = models;
+    self
+  }
+
   pub fn max_tokens(mut self, max_tokens: u32) -> Self {
     self.max_tokens = Some(max_tokens);
     self
@@ -85,22 +205,112 @@ impl ClientBuilder {
     self
   }
 
+  /// Fails the build for models that don't reason
+  pub fn reasoning_effort(mut self, effort: Option<ReasoningEffort>) -> Self {
+    self.reasoning_effort = effort;
+    self
+  }
+
   pub fn build(self) -> std::result::Result<Box<dyn AIClient>, CreateClientError> {
     let (provider, model_name) = parse_model(&self.model)?;
-    let api_key = get_provider_key(&provider)?;
-
     let max_tokens = self.max_tokens.unwrap_or(500);
+
+    // Fail on options the model rejects before looking up keys or sending anything
+    let info = self.models.find(&self.model);
+    if let Some(info) = info {
+      if self.reasoning_effort.is_some() && !info.capabilities.reasoning {
+        return Err(CreateClientError::ReasoningNotSupported(self.model));
+      }
+      info.validate(
+        max_tokens,
+        self
+          .reasoning_effort
+          .map_or(0, |effort| effort.budget_tokens()),
+      )?;
+    }
+
+    // Commands authenticate on their own
+    let api_key = match provider {
+      ModelProvider::Command | ModelProvider::Mock => String::new(),
+      _ => get_provider_key(&provider, &self.providers)?,
+    };
+
     let temperature = self.temperature.unwrap_or(0.2);
 
     let mut client: Box<dyn AIClient> = match provider {
-      ModelProvider::OpenAI => Box::new(OpenAIClient::new(api_key)),
-      ModelProvider::Anthropic => Box::new(AnthropicClient::new(api_key)),
+      ModelProvider::OpenAI => {
+        let client = OpenAIClient::new(api_key, http::build_client(&self.network)?)
+          .with_reasoning_model(info.map(|info| info.capabilities.reasoning));
+        match base_url("OPENAI_BASE_URL", &self.providers.openai) {
+          Some(base_url) => Box::new(client.with_base_url(&base_url)),
+          None => Box::new(client),
+        }
+      }
+      ModelProvider::Anthropic => {
+        let client = AnthropicClient::new(api_key, http::build_client(&self.network)?);
+        match base_url("ANTHROPIC_BASE_URL", &self.providers.anthropic) {
+          Some(base_url) => Box::new(client.with_base_url(&base_url)),
+          None => Box::new(client),
+        }
+      }
+      ModelProvider::Google => {
+        let client = GeminiClient::new(api_key, http::build_client(&self.network)?);
+        match base_url("GEMINI_BASE_URL", &self.providers.google) {
+          Some(base_url) => Box::new(client.with_base_url(&base_url)),
+          None => Box::new(client),
+        }
+      }
+      ModelProvider::Azure => {
+        let azure = &self.providers.azure;
+        let resource = env::var("AZURE_OPENAI_ENDPOINT")
+          .or_else(|_| env::var("AZURE_OPENAI_RESOURCE"))
+          .ok()
+          .or_else(|| azure.resource.clone())
+          .ok_or_else(|| {
+            CreateClientError::MissingSetting(
+              "AZURE_OPENAI_RESOURCE (or providers.azure.resource in the config)".to_string(),
+            )
+          })?;
+        let api_version = env::var("AZURE_OPENAI_API_VERSION")
+          .ok()
+          .or_else(|| azure.api_version.clone())
+          .unwrap_or_else(|| AZURE_DEFAULT_API_VERSION.to_string());
+
+        Box::new(
+          OpenAIClient::azure(
+            api_key,
+            http::build_client(&self.network)?,
+            &resource,
+            &api_version,
+          )
+          .with_reasoning_model(info.map(|info| info.capabilities.reasoning)),
+        )
+      }
+      // Without a `[providers.cmd.<name>]` entry, the name is the executable itself
+      ModelProvider::Mock => Box::new(MockClient::new(&model_name)),
+      ModelProvider::Command => Box::new(CommandClient::new(
+        self
+          .providers
+          .cmd
+          .get(&model_name)
+          .cloned()
+          .unwrap_or_else(|| CommandConfig {
+            command: model_name.clone(),
+            args: Vec::new(),
+          }),
+      )),
     };
 
     client.set_model(model_name);
     client.set_max_tokens(max_tokens);
     client.set_temperature(temperature);
 
+    if let Some(effort) = self.reasoning_effort
+      && !client.set_reasoning_effort(effort)
+    {
+      return Err(CreateClientError::ReasoningNotSupported(self.model));
+    }
+
     Ok(client)
   }
 }
@@ -108,8 +318,26 @@ impl ClientBuilder {
 pub fn create_client(
   model: &str,
   max_tokens: u32,
+  reasoning_effort: Option<ReasoningEffort>,
+  providers: &ProvidersConfig,
+  network: &NetworkConfig,
+  models: &ModelRegistry,
 ) -> std::result::Result<Box<dyn AIClient>, CreateClientError> {
-  ClientBuilder::new(model).max_tokens(max_tokens).build()
+  ClientBuilder::new(model)
+    .max_tokens(max_tokens)
+    .reasoning_effort(reasoning_effort)
+    .providers(providers.clone())
+    .network(network.clone())
+    .models(models.clone())
+    .build()
+}
+
+/// Base URL override from the environment variable, falling back to the config
+fn base_url(var: &str, config: &ProviderConfig) -> Option<String> {
+  env::var(var)
+    .ok()
+    .filter(|url| !url.is_empty())
+    .or_else(|| config.base_url.clone())
 }
 
 fn parse_model(model: &str) -> std::result::Result<(ModelProvider, String), ParseModelError> {
@@ -126,15 +354,26 @@ fn parse_model(model: &str) -> std::result::Result<(ModelProvider, String), Pars
   match (provider_str, model_name) {
     (Some("openai"), Some(m)) => Ok((ModelProvider::OpenAI, m.to_string())),
     (Some("anthropic"), Some(m)) => Ok((ModelProvider::Anthropic, m.to_string())),
+    (Some("google"), Some(m)) => Ok((ModelProvider::Google, m.to_string())),
+    (Some("azure"), Some(m)) => Ok((ModelProvider::Azure, m.to_string())),
+    (Some("mock"), Some(m)) if !m.is_empty() => Ok((ModelProvider::Mock, m.to_string())),
+    (Some("cmd"), Some(m)) if !m.is_empty() => Ok((ModelProvider::Command, m.to_string())),
     _ => Err(ParseModelError::InvalidModelFormat),
   }
 }
 
-fn get_provider_key(provider: &ModelProvider) -> std::result::Result<String, VarError> {
-  let key = match provider {
-    ModelProvider::OpenAI => "OPENAI_API_KEY",
-    ModelProvider::Anthropic => "ANTHROPIC_API_KEY",
+/// Key of the provider, see [`auth::get_api_key`] for where it is looked up
+fn get_provider_key(
+  provider: &ModelProvider,
+  providers: &ProvidersConfig,
+) -> std::result::Result<String, AuthError> {
+  let name = match provider {
+    ModelProvider::OpenAI => "openai",
+    ModelProvider::Anthropic => "anthropic",
+    ModelProvider::Google => "google",
+    ModelProvider::Azure => "azure",
+    ModelProvider::Command | ModelProvider::Mock => return Ok(String::new()),
   };
 
-  env::var(key)
+  auth::get_api_key(name, providers)
 }
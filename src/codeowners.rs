@@ -0,0 +1,210 @@
+use crate::file;
+use globset::{GlobBuilder, GlobMatcher};
+
+/// Locations searched for a CODEOWNERS file, in the order GitHub uses
+const CODEOWNERS_PATHS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+struct Rule {
+  matcher: GlobMatcher,
+  owners: Vec<String>,
+}
+
+pub struct CodeOwners {
+  rules: Vec<Rule>,
+}
+
+/// An owner together with the staged files they own
+#[derive(Debug, Clone)]
+pub struct OwnedFiles {
+  pub owner: String,
+  pub files: Vec<String>,
+}
+
+impl CodeOwners {
+  /// Load the first CODEOWNERS file found in the repository, if any
+  pub async fn load(root_dir: &str) -> Option<Self> {
+    for path in CODEOWNERS_PATHS {
+      if let Ok(content) = file::read_file(format!("{root_dir}/{path}")).await {
+        return Some(Self::parse(&content));
+      }
+    }
+
+    None
+  }
+
+  pub fn parse(content: &str) -> Self {
+    let rules = content
+      .lines()
+      .map(|line| line.trim())
+      .filter(|line| !line.is_empty() && !line.starts_with('#'))
+      .filter_map(|line| {
+        let mut parts = line.split_whitespace();
+        let pattern = parts.next()?;
+        let owners = parts
+          .take_while(|part| !part.starts_with('#'))
+          .map(|owner| owner.to_string())
+          .collect();
+        let matcher = GlobBuilder::new(&pattern_to_glob(pattern))
+          .literal_separator(true)
+          .build()
+          .ok()?
+          .compile_matcher();
+
+        Some(Rule { matcher, owners })
+      })
+      .collect();
+
+    Self { rules }
+  }
+
+  /// Owners of a file, the last matching rule wins. A rule without owners leaves the file
+  /// unowned.
+  pub fn owners_of(&self, file: &str) -> &[String] {
+    self
+      .rules
+      .iter()
+      .rev()
+      .find(|rule| rule.matcher.is_match(file))
+      .map(|rule| rule.owners.as_slice())
+      .unwrap_or_default()
+  }
+
+  /// Group the files by owner, in order of first appearance
+  pub fn owned_files(&self, files: &[String]) -> Vec<OwnedFiles> {
+    let mut owned: Vec<OwnedFiles> = Vec::new();
+
+    for file in files {
+      for owner in self.owners_of(file) {
+        match owned.iter_mut().find(|o| &o.owner == owner) {
+          Some(o) => o.files.push(file.clone()),
+          None => owned.push(OwnedFiles {
+            owner: owner.clone(),
+            files: vec![file.clone()],
+          }),
+        }
+      }
+    }
+
+    owned
+  }
+}
+
+pub fn format_owned_files(owned: &[OwnedFiles]) -> String {
+  if owned.is_empty() {
+    return "None".to_string();
+  }
+
+  let mut result = String::new();
+  for o in owned {
+    result.push_str(&format!("- {}: {}\n", o.owner, o.files.join(", ")));
+  }
+
+  result
+}
+
+/// Translate a gitignore-style CODEOWNERS pattern into a glob matched against paths relative
+/// to the repository root
+fn pattern_to_glob(pattern: &str) -> String {
+  let is_dir = pattern.ends_with('/');
+  let trimmed = pattern.trim_end_matches('/');
+
+  // A slash at the start or in the middle anchors the pattern to the root, otherwise it
+  // matches at any depth
+  let glob = match trimmed.strip_prefix('/') {
+    Some(anchored) => anchored.to_string(),
+    None if trimmed.contains('/') => trimmed.to_string(),
+    None => format!("**/{trimmed}"),
+  };
+
+  let last_segment = glob.rsplit('/').next().unwrap_or(&glob);
+  if is_dir {
+    format!("{glob}/**")
+  } else if last_segment.contains(['*', '?', '[']) {
+    // `docs/*` owns the files directly in docs, not those in its subdirectories
+    glob
+  } else {
+    // `docs` owns both a file named docs and everything below a docs directory
+    format!("{{{glob},{glob}/**}}")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn patterns_become_globs() {
+    let cases = [
+      ("*", "**/*"),
+      ("*.js", "**/*.js"),
+      ("docs", "{**/docs,**/docs/**}"),
+      ("/docs", "{docs,docs/**}"),
+      ("apps/", "**/apps/**"),
+      ("/build/logs/", "build/logs/**"),
+      ("src/api", "{src/api,src/api/**}"),
+      ("docs/*", "docs/*"),
+      ("docs/**", "docs/**"),
+      ("apps/**/test?.rs", "apps/**/test?.rs"),
+    ];
+
+    for (pattern, glob) in cases {
+      assert_eq!(pattern_to_glob(pattern), glob, "{pattern}");
+    }
+  }
+
+  #[test]
+  fn the_last_matching_rule_owns_a_file() {
+    let owners = CodeOwners::parse(
+      "# Everything else\n\
+       * @org/everyone\n\
+       *.js @org/frontend # inline comment\n\
+       /docs/* @org/writers\n\
+       apps/ @org/apps\n\
+       /build/logs/\n\
+       src/api @org/api\n",
+    );
+
+    let cases: &[(&str, &[&str])] = &[
+      ("README.md", &["@org/everyone"]),
+      ("web/index.js", &["@org/frontend"]),
+      ("docs/intro.md", &["@org/writers"]),
+      ("docs/guide/setup.md", &["@org/everyone"]),
+      ("apps/web/main.rs", &["@org/apps"]),
+      ("services/apps/main.rs", &["@org/apps"]),
+      ("build/logs/today.log", &[]),
+      ("tools/build/logs/today.log", &["@org/everyone"]),
+      ("src/api", &["@org/api"]),
+      ("src/api/handler.rs", &["@org/api"]),
+      ("lib/src/api/handler.rs", &["@org/everyone"]),
+    ];
+
+    for (file, expected) in cases {
+      assert_eq!(owners.owners_of(file), *expected, "{file}");
+    }
+  }
+
+  #[test]
+  fn files_are_grouped_by_owner() {
+    let owners = CodeOwners::parse("*.rs @rust @core\n*.md @docs\n");
+    let files = ["a.rs", "README.md", "b.rs", "data.bin"].map(String::from);
+
+    let owned = owners.owned_files(&files);
+    let owned: Vec<(&str, Vec<&str>)> = owned
+      .iter()
+      .map(|o| {
+        (
+          o.owner.as_str(),
+          o.files.iter().map(String::as_str).collect(),
+        )
+      })
+      .collect();
+    assert_eq!(
+      owned,
+      [
+        ("@rust", vec!["a.rs", "b.rs"]),
+        ("@core", vec!["a.rs", "b.rs"]),
+        ("@docs", vec!["README.md"]),
+      ]
+    );
+  }
+}
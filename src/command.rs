@@ -0,0 +1,192 @@
+use crate::client::{
+  AIClient, ClientError, GenerateResponseResult, ReasoningEffort, ResponseFuture, Result, UsageInfo,
+};
+use crate::config::CommandConfig;
+use crate::message::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Request written to the command's stdin
+#[derive(Debug, Serialize)]
+struct CommandRequest {
+  model: String,
+  system: String,
+  messages: Vec<Message>,
+  max_tokens: u32,
+  temperature: f32,
+  /// `minimal`, `low`, `medium` or `high` when `--reasoning-effort` is given
+  #[serde(skip_serializing_if = "Option::is_none")]
+  reasoning_effort: Option<String>,
+  /// JSON schema the message must match, for commands that support structured output
+  #[serde(skip_serializing_if = "Option::is_none")]
+  schema: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+  role: String,
+  content: String,
+}
+
+/// Response read from the command's stdout
+#[derive(Debug, Deserialize)]
+struct CommandResponse {
+  message: String,
+  #[serde(default)]
+  usage: CommandUsage,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CommandUsage {
+  input_tokens: u32,
+  output_tokens: u32,
+  total_tokens: Option<u32>,
+  reasoning_tokens: u32,
+}
+
+/// Runs a user-configured executable for every request, e.g. an internal gateway, the `llm` CLI
+/// or a test double
+pub struct CommandClient {
+  program: String,
+  args: Vec<String>,
+  model: String,
+  max_tokens: u32,
+  temperature: f32,
+  reasoning_effort: Option<ReasoningEffort>,
+}
+
+impl CommandClient {
+  pub fn new(config: CommandConfig) -> Self {
+    Self {
+      program: config.command,
+      args: config.args,
+      model: String::new(),
+      max_tokens: 500,
+      temperature: 0.7,
+      reasoning_effort: None,
+    }
+  }
+}
+
+impl AIClient for CommandClient {
+  fn set_model(&mut self, model: String) {
+    self.model = model;
+  }
+
+  fn set_max_tokens(&mut self, max_tokens: u32) {
+    self.max_tokens = max_tokens;
+  }
+
+  fn set_temperature(&mut self, temperature: f32) {
+    self.temperature = temperature;
+  }
+
+  /// Passed on to the command, which decides what to do with it
+  fn set_reasoning_effort(&mut self, effort: ReasoningEffort) -> bool {
+    self.reasoning_effort = Some(effort);
+    true
+  }
+
+  fn generate_response(&self, system_prompt: String, user_prompt: String) -> ResponseFuture<'_> {
+    Box::pin(self.generate_response_impl(system_prompt, user_prompt, None))
+  }
+
+  fn generate_structured_response(
+    &self,
+    system_prompt: String,
+    user_prompt: String,
+    schema: JsonSchema,
+  ) -> ResponseFuture<'_> {
+    Box::pin(self.generate_response_impl(system_prompt, user_prompt, Some(schema)))
+  }
+}
+
+impl CommandClient {
+  /// With a schema, it is passed in the request and also described in the system prompt, so
+  /// commands that only forward the prompts still get JSON back
+  async fn generate_response_impl(
+    &self,
+    system_prompt: String,
+    user_prompt: String,
+    schema: Option<JsonSchema>,
+  ) -> Result<GenerateResponseResult> {
+    let system_prompt = match &schema {
+      Some(schema) => format!(
+        "{system_prompt}\n\nRespond only with a JSON object matching this JSON schema, without code fences or explanations:\n{}",
+        schema.schema
+      ),
+      None => system_prompt,
+    };
+
+    let request = CommandRequest {
+      model: self.model.clone(),
+      system: system_prompt,
+      messages: vec![Message {
+        role: "user".to_string(),
+        content: user_prompt,
+      }],
+      max_tokens: self.max_tokens,
+      temperature: self.temperature,
+      reasoning_effort: self
+        .reasoning_effort
+        .map(|effort| effort.as_str().to_string()),
+      schema: schema.map(|schema| schema.schema),
+    };
+    let input =
+      serde_json::to_vec(&request).map_err(|e| ClientError::FailedToSend(e.to_string()))?;
+    let failed = |e: std::io::Error| ClientError::FailedToSend(format!("{}: {e}", self.program));
+
+    let mut child = Command::new(&self.program)
+      .args(&self.args)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(failed)?;
+
+    // Write while reading the output, so a command exiting before it read its input reports
+    // its stderr instead of a broken pipe. Dropping stdin at the end closes it.
+    let stdin = child.stdin.take();
+    let write = async move {
+      if let Some(mut stdin) = stdin {
+        stdin.write_all(&input).await?;
+      }
+      Ok::<(), std::io::Error>(())
+    };
+    let (written, output) = tokio::join!(write, child.wait_with_output());
+    let output = output.map_err(failed)?;
+
+    if !output.status.success() {
+      let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+      return Err(ClientError::RequestFailed(format!(
+        "{} exited with {}: {}",
+        self.program, output.status, stderr
+      )));
+    }
+    written.map_err(failed)?;
+
+    let response: CommandResponse =
+      serde_json::from_slice(&output.stdout).map_err(|_| ClientError::FailedToParseResponse)?;
+
+    let usage = response.usage;
+    let usage = UsageInfo {
+      input_tokens: usage.input_tokens,
+      output_tokens: usage.output_tokens,
+      total_tokens: usage
+        .total_tokens
+        .unwrap_or(usage.input_tokens + usage.output_tokens),
+      cache_creation_input_tokens: 0,
+      cache_read_input_tokens: 0,
+      reasoning_tokens: usage.reasoning_tokens,
+    };
+
+    Ok(GenerateResponseResult {
+      message: response.message.trim().to_string(),
+      usage,
+    })
+  }
+}
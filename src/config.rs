@@ -0,0 +1,296 @@
+use crate::file;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+
+const REPO_CONFIG_FILE: &str = ".gen-commit.toml";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+  #[error("Failed to read config file {0}")]
+  FailedToRead(String),
+  #[error("Failed to parse config file {0}: {1}")]
+  FailedToParse(String, toml::de::Error),
+}
+
+type Result<T> = std::result::Result<T, ConfigError>;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+  pub issues: IssuesConfig,
+  pub trailers: TrailersConfig,
+  pub scopes: ScopesConfig,
+  pub codeowners: CodeOwnersConfig,
+  pub classify: ClassifyConfig,
+  pub breaking: BreakingConfig,
+  pub cache: CacheConfig,
+  pub providers: ProvidersConfig,
+  pub network: NetworkConfig,
+  /// Extends the built-in model catalog, keyed by `provider:model`
+  pub models: HashMap<String, ModelConfig>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IssueConvention {
+  /// Append one footer line per issue key
+  #[default]
+  Footer,
+  /// Prepend the issue keys to the subject line
+  Prefix,
+  /// Detect issue keys but leave the message untouched
+  None,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct IssuesConfig {
+  /// Regular expressions matched against the branch name. The first capture group (or the
+  /// whole match when there is none) is used as the issue key.
+  pub patterns: Vec<String>,
+  pub convention: IssueConvention,
+  /// Footer line template, `{key}` is replaced with the issue key
+  pub footer_format: String,
+  /// Subject prefix template, `{key}` is replaced with the comma-separated issue keys
+  pub prefix_format: String,
+}
+
+impl Default for IssuesConfig {
+  fn default() -> Self {
+    Self {
+      patterns: vec![
+        r"\b([A-Z][A-Z0-9]+-\d+)\b".to_string(),
+        r"(?:^|/)(#\d+)\b".to_string(),
+      ],
+      convention: IssueConvention::default(),
+      footer_format: "Refs: {key}".to_string(),
+      prefix_format: "[{key}] ".to_string(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TrailersConfig {
+  /// Always add a `Signed-off-by` trailer
+  pub signoff: bool,
+  /// Always add a `Generated-by` trailer recording the model used
+  pub generated_by: bool,
+  /// Co-author roster, maps an alias to `Name <email>`
+  pub co_authors: HashMap<String, String>,
+}
+
+/// What happens to a scope the model picked that is not in the scopes file or doesn't match
+/// the staged files
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScopeValidation {
+  /// Replace it with the closest allowed scope
+  #[default]
+  Repair,
+  /// Keep it and print a warning
+  Warn,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScopesConfig {
+  pub validation: ScopeValidation,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CodeOwnersConfig {
+  /// Add a `Code-owners` trailer listing the owners of the staged files
+  pub trailer: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ClassifyConfig {
+  /// Rules checked in order before the built-in ones, the first match wins
+  pub rules: Vec<ClassifyRule>,
+  /// Language overrides by file extension, e.g. `mdx = "MDX"`
+  pub languages: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClassifyRule {
+  /// Glob relative to the repository root, e.g. `apps/web/**`
+  pub pattern: String,
+  pub category: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BreakingConfig {
+  /// Detect breaking changes to the public API and pass them to the model
+  pub detect: bool,
+  /// Also mark the subject with `!` and add the footer draft, whatever the model answered
+  pub mark: bool,
+}
+
+impl Default for BreakingConfig {
+  fn default() -> Self {
+    Self {
+      detect: true,
+      mark: false,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+  /// Cache the change analysis, `--no-cache` disables it for a single run
+  pub enabled: bool,
+  /// Also cache commit message generation for identical prompts
+  pub generation: bool,
+  /// Entries older than this are ignored and pruned
+  pub ttl_hours: u64,
+  /// The oldest entries are pruned once the cache grows beyond this size
+  pub max_size_kb: u64,
+}
+
+impl Default for CacheConfig {
+  fn default() -> Self {
+    Self {
+      enabled: true,
+      generation: false,
+      ttl_hours: 7 * 24,
+      max_size_kb: 10 * 1024,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ProvidersConfig {
+  pub anthropic: ProviderConfig,
+  pub openai: ProviderConfig,
+  pub google: ProviderConfig,
+  pub azure: AzureConfig,
+  /// External commands addressed as `cmd:<name>`
+  pub cmd: HashMap<String, CommandConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ProviderConfig {
+  /// API root, e.g. a proxy or gateway. The `<PROVIDER>_BASE_URL` environment variable takes
+  /// precedence.
+  pub base_url: Option<String>,
+  /// Shell command printing the API key, e.g. `pass show openai`
+  pub key_command: Option<String>,
+  /// File containing the API key
+  pub key_file: Option<String>,
+}
+
+/// Azure OpenAI settings, the `AZURE_OPENAI_*` environment variables take precedence
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AzureConfig {
+  /// Resource name, e.g. `my-resource` for `https://my-resource.openai.azure.com`, or the full
+  /// endpoint URL
+  pub resource: Option<String>,
+  pub api_version: Option<String>,
+  pub api_key: Option<String>,
+  /// Shell command printing the API key, e.g. `pass show azure-openai`
+  pub key_command: Option<String>,
+  /// File containing the API key
+  pub key_file: Option<String>,
+}
+
+/// An executable that receives the request as JSON on stdin and prints the response as JSON
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CommandConfig {
+  pub command: String,
+  pub args: Vec<String>,
+}
+
+/// HTTP settings of the API providers, e.g. for a corporate egress proxy with TLS inspection
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+  /// Proxy for all requests, instead of `HTTPS_PROXY`/`HTTP_PROXY`
+  pub proxy: Option<String>,
+  /// Comma-separated hosts reached without the proxy, instead of `NO_PROXY`
+  pub no_proxy: Option<String>,
+  /// PEM file with additional root certificates to trust
+  pub ca_bundle: Option<String>,
+  /// PEM client certificate chain for mutual TLS, used with `client_key`
+  pub client_cert: Option<String>,
+  /// PEM PKCS#8 private key of `client_cert`
+  pub client_key: Option<String>,
+  /// Timeout of a whole request, 0 disables it
+  pub timeout_secs: u64,
+}
+
+impl Default for NetworkConfig {
+  fn default() -> Self {
+    Self {
+      proxy: None,
+      no_proxy: None,
+      ca_bundle: None,
+      client_cert: None,
+      client_key: None,
+      timeout_secs: 120,
+    }
+  }
+}
+
+/// Limits, parameters and prices of a model. Unset fields keep the built-in values, or those of
+/// the model it is a snapshot of.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ModelConfig {
+  pub context_window: Option<u32>,
+  pub max_output_tokens: Option<u32>,
+  pub temperature: Option<bool>,
+  pub streaming: Option<bool>,
+  pub structured_output: Option<bool>,
+  pub caching: Option<bool>,
+  pub reasoning: Option<bool>,
+  /// USD per million input tokens
+  pub input_price: Option<f64>,
+  /// USD per million output tokens
+  pub output_price: Option<f64>,
+  /// USD per million input tokens read from the prompt cache
+  pub cached_input_price: Option<f64>,
+}
+
+/// Load configuration from `path` if given, otherwise from `.gen-commit.toml` in the repository
+/// root, falling back to the user config file. Missing files yield the default configuration.
+pub async fn load_config(path: Option<&str>, root_dir: &str) -> Result<Config> {
+  let path = match path {
+    Some(path) => Some(path.to_string()),
+    None => [
+      Some(format!("{root_dir}/{REPO_CONFIG_FILE}")),
+      user_config_path(),
+    ]
+    .into_iter()
+    .flatten()
+    .find(|path| file::file_exists(path)),
+  };
+
+  let Some(path) = path else {
+    return Ok(Config::default());
+  };
+
+  let content = file::read_file(&path)
+    .await
+    .map_err(|_| ConfigError::FailedToRead(path.clone()))?;
+
+  toml::from_str(&content).map_err(|e| ConfigError::FailedToParse(path, e))
+}
+
+fn user_config_path() -> Option<String> {
+  let config_dir = env::var("XDG_CONFIG_HOME")
+    .ok()
+    .filter(|dir| !dir.is_empty())
+    .or_else(|| env::var("HOME").ok().map(|home| format!("{home}/.config")))?;
+
+  Some(format!("{config_dir}/gen-commit/config.toml"))
+}
@@ -0,0 +1,734 @@
+use crate::git;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use yaml_rust2::YamlLoader;
+
+/// Most changes listed in the prompt or in a suggested commit body
+const MAX_LINES: usize = 50;
+
+/// Dependencies keyed by `(name, dev)` with their version requirement or resolved version
+type Dependencies = BTreeMap<(String, bool), String>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ManifestKind {
+  CargoToml,
+  PackageJson,
+  PyProject,
+  GoMod,
+  Requirements,
+  CargoLock,
+  PackageLock,
+  PnpmLock,
+  GoSum,
+}
+
+impl ManifestKind {
+  fn from_path(path: &str) -> Option<Self> {
+    let filename = path.rsplit('/').next().unwrap_or(path);
+    match filename {
+      "Cargo.toml" => Some(Self::CargoToml),
+      "package.json" => Some(Self::PackageJson),
+      "pyproject.toml" => Some(Self::PyProject),
+      "go.mod" => Some(Self::GoMod),
+      "Cargo.lock" => Some(Self::CargoLock),
+      "package-lock.json" => Some(Self::PackageLock),
+      "pnpm-lock.yaml" => Some(Self::PnpmLock),
+      "go.sum" => Some(Self::GoSum),
+      name if name.starts_with("requirements") && name.ends_with(".txt") => {
+        Some(Self::Requirements)
+      }
+      _ => None,
+    }
+  }
+
+  fn is_lockfile(&self) -> bool {
+    matches!(
+      self,
+      Self::CargoLock | Self::PackageLock | Self::PnpmLock | Self::GoSum
+    )
+  }
+
+  fn parse(&self, content: &str) -> Dependencies {
+    if content.is_empty() {
+      return Dependencies::new();
+    }
+
+    match self {
+      Self::CargoToml => parse_cargo_toml(content),
+      Self::PackageJson => parse_package_json(content),
+      Self::PyProject => parse_pyproject(content),
+      Self::GoMod => parse_go_mod(content),
+      Self::Requirements => parse_requirements(content),
+      Self::CargoLock => parse_cargo_lock(content),
+      Self::PackageLock => parse_package_lock(content),
+      Self::PnpmLock => parse_pnpm_lock(content),
+      Self::GoSum => parse_go_sum(content),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct DependencyChange {
+  /// Manifest or lockfile the change was found in
+  pub file: String,
+  pub name: String,
+  /// Only needed for development, e.g. `dev-dependencies` or `devDependencies`
+  pub dev: bool,
+  pub lockfile: bool,
+  pub old_version: Option<String>,
+  pub new_version: Option<String>,
+}
+
+impl DependencyChange {
+  fn describe(&self) -> String {
+    match (&self.old_version, &self.new_version) {
+      (None, Some(new)) if new == "*" => format!("Add {}", self.name),
+      (None, Some(new)) => format!("Add {} {}", self.name, new),
+      (Some(_), None) => format!("Remove {}", self.name),
+      (Some(old), Some(new)) => format!("Bump {} from {} to {}", self.name, old, new),
+      (None, None) => self.name.clone(),
+    }
+  }
+}
+
+/// Compare the dependencies of every staged manifest and lockfile between `HEAD` and the
+/// index. Lockfiles are usually in the ignore list, so the paths come from the full list of
+/// staged files rather than from the filtered diff.
+pub async fn detect_dependency_changes(staged_files: &[String]) -> Vec<DependencyChange> {
+  let mut changes = Vec::new();
+
+  for path in staged_files {
+    let Some(kind) = ManifestKind::from_path(path) else {
+      continue;
+    };
+
+    let old_content = git::show_blob(&format!("HEAD:{path}"))
+      .await
+      .unwrap_or_default();
+    let new_content = git::show_blob(&format!(":{path}"))
+      .await
+      .unwrap_or_default();
+
+    changes.extend(compare_dependencies(
+      path,
+      kind.is_lockfile(),
+      &kind.parse(&old_content),
+      &kind.parse(&new_content),
+    ));
+  }
+
+  changes
+}
+
+fn compare_dependencies(
+  file: &str,
+  lockfile: bool,
+  old: &Dependencies,
+  new: &Dependencies,
+) -> Vec<DependencyChange> {
+  let mut keys: Vec<&(String, bool)> = old.keys().chain(new.keys()).collect();
+  keys.sort();
+  keys.dedup();
+
+  keys
+    .into_iter()
+    .filter_map(|key| {
+      let old_version = old.get(key).cloned();
+      let new_version = new.get(key).cloned();
+      (old_version != new_version).then(|| DependencyChange {
+        file: file.to_string(),
+        name: key.0.clone(),
+        dev: key.1,
+        lockfile,
+        old_version,
+        new_version,
+      })
+    })
+    .collect()
+}
+
+/// Propose a conventional commit subject and body for the dependency changes. Manifest
+/// changes are preferred; lockfile changes are only used for lockfile-only commits.
+pub fn suggest_commit(changes: &[DependencyChange]) -> Option<(String, Vec<String>)> {
+  let manifest_changes: Vec<&DependencyChange> = changes.iter().filter(|c| !c.lockfile).collect();
+  let relevant: Vec<&DependencyChange> = if manifest_changes.is_empty() {
+    changes.iter().collect()
+  } else {
+    manifest_changes
+  };
+
+  // A dependency changed in several manifests is listed once
+  let mut descriptions: Vec<String> = relevant.iter().map(|c| c.describe()).collect();
+  descriptions.sort();
+  descriptions.dedup();
+  let first = descriptions.first()?;
+
+  let commit_type = if relevant.iter().all(|c| c.dev) {
+    "chore(deps)"
+  } else {
+    "build(deps)"
+  };
+
+  if descriptions.len() == 1 {
+    let mut subject = first.clone();
+    subject[..1].make_ascii_lowercase();
+    return Some((format!("{commit_type}: {subject}"), Vec::new()));
+  }
+
+  let mut body: Vec<String> = descriptions
+    .iter()
+    .take(MAX_LINES)
+    .map(|d| format!("- {d}"))
+    .collect();
+  if descriptions.len() > MAX_LINES {
+    body.push(format!("- ... and {} more", descriptions.len() - MAX_LINES));
+  }
+  Some((format!("{commit_type}: update dependencies"), body))
+}
+
+/// Format the changes for the prompt, capped so a large lockfile update can't flood it
+pub fn format_dependency_changes(changes: &[DependencyChange]) -> String {
+  if changes.is_empty() {
+    return "None".to_string();
+  }
+
+  let mut result = String::new();
+  for change in changes.iter().take(MAX_LINES) {
+    let dev = if change.dev { " (dev)" } else { "" };
+    result.push_str(&format!(
+      "- {}: {}{}\n",
+      change.file,
+      change.describe(),
+      dev
+    ));
+  }
+  if changes.len() > MAX_LINES {
+    result.push_str(&format!("- ... and {} more\n", changes.len() - MAX_LINES));
+  }
+
+  if let Some((subject, body)) = suggest_commit(changes) {
+    result.push_str(&format!("\nSuggested commit:\n{subject}\n"));
+    if !body.is_empty() {
+      result.push('\n');
+      for line in body {
+        result.push_str(&line);
+        result.push('\n');
+      }
+    }
+  }
+
+  result
+}
+
+fn parse_cargo_toml(content: &str) -> Dependencies {
+  let mut deps = Dependencies::new();
+  let Ok(manifest) = toml::from_str::<toml::Table>(content) else {
+    return deps;
+  };
+
+  let mut add_table = |table: Option<&toml::Value>, dev: bool| {
+    let Some(table) = table.and_then(|t| t.as_table()) else {
+      return;
+    };
+    for (name, spec) in table {
+      let version = match spec {
+        toml::Value::String(version) => version.clone(),
+        toml::Value::Table(spec) => cargo_dependency_source(spec),
+        _ => continue,
+      };
+      let name = spec.get("package").and_then(|p| p.as_str()).unwrap_or(name);
+      deps.insert((name.to_string(), dev), version);
+    }
+  };
+
+  add_table(manifest.get("dependencies"), false);
+  add_table(manifest.get("build-dependencies"), false);
+  add_table(manifest.get("dev-dependencies"), true);
+  add_table(
+    manifest
+      .get("workspace")
+      .and_then(|w| w.get("dependencies")),
+    false,
+  );
+
+  if let Some(targets) = manifest.get("target").and_then(|t| t.as_table()) {
+    for target in targets.values() {
+      add_table(target.get("dependencies"), false);
+      add_table(target.get("build-dependencies"), false);
+      add_table(target.get("dev-dependencies"), true);
+    }
+  }
+
+  deps
+}
+
+/// `{ version = "1" }`, `{ git = "...", tag = "v1" }`, `{ path = "..." }` or `{ workspace = true }`
+fn cargo_dependency_source(spec: &toml::Table) -> String {
+  let get = |key: &str| spec.get(key).and_then(|v| v.as_str());
+
+  if let Some(version) = get("version") {
+    return version.to_string();
+  }
+  if let Some(git) = get("git") {
+    let reference = get("tag").or(get("rev")).or(get("branch"));
+    return match reference {
+      Some(reference) => format!("git {git}#{reference}"),
+      None => format!("git {git}"),
+    };
+  }
+  if let Some(path) = get("path") {
+    return format!("path {path}");
+  }
+  if spec.get("workspace").and_then(|v| v.as_bool()) == Some(true) {
+    return "workspace".to_string();
+  }
+
+  "*".to_string()
+}
+
+fn parse_package_json(content: &str) -> Dependencies {
+  let mut deps = Dependencies::new();
+  let Ok(json) = serde_json::from_str::<Value>(content) else {
+    return deps;
+  };
+
+  let sections = [
+    ("dependencies", false),
+    ("peerDependencies", false),
+    ("optionalDependencies", false),
+    ("devDependencies", true),
+  ];
+  for (section, dev) in sections {
+    let Some(entries) = json.get(section).and_then(Value::as_object) else {
+      continue;
+    };
+    for (name, version) in entries {
+      if let Some(version) = version.as_str() {
+        deps.insert((name.clone(), dev), version.to_string());
+      }
+    }
+  }
+
+  deps
+}
+
+fn parse_pyproject(content: &str) -> Dependencies {
+  let mut deps = Dependencies::new();
+  let Ok(manifest) = toml::from_str::<toml::Table>(content) else {
+    return deps;
+  };
+
+  // PEP 621 `[project]`
+  if let Some(project) = manifest.get("project") {
+    let requirements = project
+      .get("dependencies")
+      .and_then(|d| d.as_array())
+      .into_iter()
+      .flatten()
+      .map(|r| (r, false));
+    let optional = project
+      .get("optional-dependencies")
+      .and_then(|d| d.as_table())
+      .into_iter()
+      .flat_map(|groups| groups.values())
+      .filter_map(|group| group.as_array())
+      .flatten()
+      .map(|r| (r, true));
+
+    for (requirement, dev) in requirements.chain(optional) {
+      if let Some((name, version)) = requirement.as_str().and_then(parse_pep508) {
+        deps.insert((name, dev), version);
+      }
+    }
+  }
+
+  // Poetry `[tool.poetry.dependencies]` and `[tool.poetry.group.<name>.dependencies]`
+  let poetry = manifest.get("tool").and_then(|t| t.get("poetry"));
+  let mut add_poetry_table = |table: Option<&toml::Value>, dev: bool| {
+    let Some(table) = table.and_then(|t| t.as_table()) else {
+      return;
+    };
+    for (name, spec) in table {
+      if name == "python" {
+        continue;
+      }
+      let version = match spec {
+        toml::Value::String(version) => version.clone(),
+        toml::Value::Table(spec) => spec
+          .get("version")
+          .and_then(|v| v.as_str())
+          .unwrap_or("*")
+          .to_string(),
+        _ => continue,
+      };
+      deps.insert((name.clone(), dev), version);
+    }
+  };
+  if let Some(poetry) = poetry {
+    add_poetry_table(poetry.get("dependencies"), false);
+    add_poetry_table(poetry.get("dev-dependencies"), true);
+    if let Some(groups) = poetry.get("group").and_then(|g| g.as_table()) {
+      for group in groups.values() {
+        add_poetry_table(group.get("dependencies"), true);
+      }
+    }
+  }
+
+  deps
+}
+
+fn parse_requirements(content: &str) -> Dependencies {
+  content
+    .lines()
+    .map(|line| line.split('#').next().unwrap_or_default().trim())
+    .filter(|line| !line.is_empty() && !line.starts_with('-'))
+    .filter_map(parse_pep508)
+    .map(|(name, version)| ((name, false), version))
+    .collect()
+}
+
+/// Split a PEP 508 requirement such as `requests[socks]>=2.0; python_version<"3.8"` into its
+/// name and version specifier
+fn parse_pep508(requirement: &str) -> Option<(String, String)> {
+  let requirement = requirement.split(';').next()?.trim();
+  let name_end = requirement
+    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'))
+    .unwrap_or(requirement.len());
+  let name = &requirement[..name_end];
+  if name.is_empty() {
+    return None;
+  }
+
+  let rest = requirement[name_end..].trim();
+  // Drop extras like `[socks]`
+  let rest = match rest.strip_prefix('[') {
+    Some(extras) => extras
+      .split_once(']')
+      .map(|(_, r)| r)
+      .unwrap_or_default()
+      .trim(),
+    None => rest,
+  };
+  let version = if rest.is_empty() { "*" } else { rest };
+
+  Some((name.to_lowercase(), version.to_string()))
+}
+
+fn parse_go_mod(content: &str) -> Dependencies {
+  let mut deps = Dependencies::new();
+  let mut in_require = false;
+
+  for line in content.lines() {
+    let line = line.trim();
+    let requirement = if in_require {
+      if line == ")" {
+        in_require = false;
+        continue;
+      }
+      line
+    } else if line == "require (" {
+      in_require = true;
+      continue;
+    } else if let Some(requirement) = line.strip_prefix("require ") {
+      requirement
+    } else {
+      continue;
+    };
+
+    let mut parts = requirement.split_whitespace();
+    if let (Some(module), Some(version)) = (parts.next(), parts.next()) {
+      let indirect = requirement.ends_with("// indirect");
+      // Indirect requirements are treated like lockfile noise, not development dependencies
+      if !indirect {
+        deps.insert((module.to_string(), false), version.to_string());
+      }
+    }
+  }
+
+  deps
+}
+
+/// Multiple resolved versions of the same package are joined, e.g. `0.1.5, 0.2.1`
+fn join_versions(versions: BTreeMap<String, Vec<String>>) -> Dependencies {
+  versions
+    .into_iter()
+    .map(|(name, mut versions)| {
+      versions.sort();
+      versions.dedup();
+      ((name, false), versions.join(", "))
+    })
+    .collect()
+}
+
+fn parse_cargo_lock(content: &str) -> Dependencies {
+  let mut versions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+  let Ok(lock) = toml::from_str::<toml::Table>(content) else {
+    return Dependencies::new();
+  };
+
+  let packages = lock.get("package").and_then(|p| p.as_array());
+  for package in packages.into_iter().flatten() {
+    let name = package.get("name").and_then(|n| n.as_str());
+    let version = package.get("version").and_then(|v| v.as_str());
+    if let (Some(name), Some(version)) = (name, version) {
+      versions
+        .entry(name.to_string())
+        .or_default()
+        .push(version.to_string());
+    }
+  }
+
+  join_versions(versions)
+}
+
+fn parse_package_lock(content: &str) -> Dependencies {
+  let mut versions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+  let Ok(lock) = serde_json::from_str::<Value>(content) else {
+    return Dependencies::new();
+  };
+
+  let packages = lock.get("packages").and_then(Value::as_object);
+  for (path, package) in packages.into_iter().flatten() {
+    let Some((_, name)) = path.rsplit_once("node_modules/") else {
+      continue;
+    };
+    if let Some(version) = package.get("version").and_then(Value::as_str) {
+      versions
+        .entry(name.to_string())
+        .or_default()
+        .push(version.to_string());
+    }
+  }
+
+  join_versions(versions)
+}
+
+fn parse_pnpm_lock(content: &str) -> Dependencies {
+  let mut versions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+  let Ok(documents) = YamlLoader::load_from_str(content) else {
+    return Dependencies::new();
+  };
+
+  let packages = documents
+    .first()
+    .and_then(|lock| lock["packages"].as_hash());
+  for key in packages.into_iter().flat_map(|p| p.keys()) {
+    // `/name@1.0.0`, `@scope/name@1.0.0` or `name@1.0.0(peer@2.0.0)`
+    let Some(key) = key.as_str() else {
+      continue;
+    };
+    let key = key.trim_start_matches('/');
+    let key = key.split('(').next().unwrap_or(key);
+    // The leading `@` of a scoped name is not the version separator
+    let Some(at) = key
+      .char_indices()
+      .skip(1)
+      .filter(|&(_, c)| c == '@')
+      .map(|(i, _)| i)
+      .last()
+    else {
+      continue;
+    };
+    versions
+      .entry(key[..at].to_string())
+      .or_default()
+      .push(key[at + 1..].to_string());
+  }
+
+  join_versions(versions)
+}
+
+fn parse_go_sum(content: &str) -> Dependencies {
+  let mut versions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+  for line in content.lines() {
+    let mut parts = line.split_whitespace();
+    let (Some(module), Some(version)) = (parts.next(), parts.next()) else {
+      continue;
+    };
+    // Every module also has a `/go.mod` hash line for the same version
+    let version = version.trim_end_matches("/go.mod");
+    versions
+      .entry(module.to_string())
+      .or_default()
+      .push(version.to_string());
+  }
+
+  join_versions(versions)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn deps(entries: &[(&str, bool, &str)]) -> Dependencies {
+    entries
+      .iter()
+      .map(|&(name, dev, version)| ((name.to_string(), dev), version.to_string()))
+      .collect()
+  }
+
+  #[test]
+  fn parses_every_format() {
+    let cases: &[(&str, &str, Dependencies)] = &[
+      (
+        "Cargo.toml",
+        r#"
+[dependencies]
+serde = "1"
+renamed = { package = "tokio", version = "1.40" }
+local = { path = "../local" }
+[dev-dependencies]
+tempfile = { git = "https://github.com/x/tempfile", tag = "v3" }
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+"#,
+        deps(&[
+          ("serde", false, "1"),
+          ("tokio", false, "1.40"),
+          ("local", false, "path ../local"),
+          ("tempfile", true, "git https://github.com/x/tempfile#v3"),
+          ("libc", false, "0.2"),
+        ]),
+      ),
+      (
+        "package.json",
+        r#"{
+  "dependencies": { "@scope/pkg": "^1.0.0", "react": "18.2.0" },
+  "devDependencies": { "@types/node": "^20" }
+}"#,
+        deps(&[
+          ("@scope/pkg", false, "^1.0.0"),
+          ("react", false, "18.2.0"),
+          ("@types/node", true, "^20"),
+        ]),
+      ),
+      (
+        "pyproject.toml",
+        r#"
+[project]
+dependencies = ["Requests[socks]>=2.0; python_version<'3.8'", "click"]
+[project.optional-dependencies]
+test = ["pytest==8.0"]
+"#,
+        deps(&[
+          ("requests", false, ">=2.0"),
+          ("click", false, "*"),
+          ("pytest", true, "==8.0"),
+        ]),
+      ),
+      (
+        "requirements-dev.txt",
+        "# tools\n-r requirements.txt\nblack==24.1 # formatter\nruff\n",
+        deps(&[("black", false, "==24.1"), ("ruff", false, "*")]),
+      ),
+      (
+        "go.mod",
+        "module example.com/app\n\nrequire github.com/a/b v1.2.0\n\nrequire (\n\tgolang.org/x/sys v0.20.0\n\tgolang.org/x/text v0.14.0 // indirect\n)\n",
+        deps(&[
+          ("github.com/a/b", false, "v1.2.0"),
+          ("golang.org/x/sys", false, "v0.20.0"),
+        ]),
+      ),
+      (
+        "Cargo.lock",
+        "[[package]]\nname = \"rand\"\nversion = \"0.8.5\"\n\n[[package]]\nname = \"rand\"\nversion = \"0.7.3\"\n",
+        deps(&[("rand", false, "0.7.3, 0.8.5")]),
+      ),
+      (
+        "package-lock.json",
+        r#"{
+  "packages": {
+    "": { "version": "1.0.0" },
+    "node_modules/@scope/pkg": { "version": "1.2.3" },
+    "node_modules/a/node_modules/@scope/pkg": { "version": "1.0.0" }
+  }
+}"#,
+        deps(&[("@scope/pkg", false, "1.0.0, 1.2.3")]),
+      ),
+      (
+        "pnpm-lock.yaml",
+        r#"
+packages:
+  /lodash@4.17.21:
+    resolution: {}
+  '@scope/pkg@1.2.3':
+    resolution: {}
+  react-dom@18.2.0(react@18.2.0):
+    resolution: {}
+  /:
+    resolution: {}
+  éclair:
+    resolution: {}
+  ünicode@1.0.0:
+    resolution: {}
+"#,
+        deps(&[
+          ("lodash", false, "4.17.21"),
+          ("@scope/pkg", false, "1.2.3"),
+          ("react-dom", false, "18.2.0"),
+          ("ünicode", false, "1.0.0"),
+        ]),
+      ),
+      (
+        "go.sum",
+        "github.com/a/b v1.2.0 h1:abc=\ngithub.com/a/b v1.2.0/go.mod h1:def=\n",
+        deps(&[("github.com/a/b", false, "v1.2.0")]),
+      ),
+    ];
+
+    for (path, content, expected) in cases {
+      let kind = ManifestKind::from_path(path).unwrap();
+      assert_eq!(&kind.parse(content), expected, "{path}");
+    }
+  }
+
+  #[test]
+  fn unparsable_content_has_no_dependencies() {
+    for path in [
+      "Cargo.toml",
+      "package.json",
+      "package-lock.json",
+      "pnpm-lock.yaml",
+    ] {
+      let kind = ManifestKind::from_path(path).unwrap();
+      assert!(kind.parse("{ not valid").is_empty(), "{path}");
+    }
+  }
+
+  fn bump(file: &str, name: &str) -> DependencyChange {
+    DependencyChange {
+      file: file.to_string(),
+      name: name.to_string(),
+      dev: false,
+      lockfile: false,
+      old_version: Some("1.0".to_string()),
+      new_version: Some("1.1".to_string()),
+    }
+  }
+
+  #[test]
+  fn suggested_commits_list_each_change_once() {
+    let changes = [
+      bump("a/Cargo.toml", "serde"),
+      bump("a/Cargo.toml", "tokio"),
+      bump("b/Cargo.toml", "serde"),
+    ];
+    let (subject, body) = suggest_commit(&changes).unwrap();
+    assert_eq!(subject, "build(deps): update dependencies");
+    assert_eq!(
+      body,
+      [
+        "- Bump serde from 1.0 to 1.1",
+        "- Bump tokio from 1.0 to 1.1"
+      ]
+    );
+
+    let changes: Vec<DependencyChange> = (0..60)
+      .map(|i| bump("Cargo.toml", &format!("crate-{i:02}")))
+      .collect();
+    let (_, body) = suggest_commit(&changes).unwrap();
+    assert_eq!(body.len(), MAX_LINES + 1);
+    assert_eq!(body.last().unwrap(), "- ... and 10 more");
+  }
+}
@@ -0,0 +1,551 @@
+use globset::{Glob, GlobMatcher};
+
+/// How a file changed, mirroring git's `--name-status` letters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+  Added,
+  Modified,
+  Deleted,
+  Renamed,
+  Copied,
+}
+
+impl ChangeKind {
+  pub fn letter(&self) -> char {
+    match self {
+      ChangeKind::Added => 'A',
+      ChangeKind::Modified => 'M',
+      ChangeKind::Deleted => 'D',
+      ChangeKind::Renamed => 'R',
+      ChangeKind::Copied => 'C',
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Hunk {
+  /// The `@@ -a,b +c,d @@ context` line
+  pub header: String,
+  pub old_start: usize,
+  pub new_start: usize,
+  /// Body lines including their ` `, `+`, `-` or `\` prefix
+  pub lines: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+  pub path: String,
+  /// Previous path of a renamed or copied file
+  pub old_path: Option<String>,
+  pub kind: ChangeKind,
+  pub binary: bool,
+  /// Lines between `diff --git` and the first hunk, e.g. `index`, `---` and `+++`
+  pub header: Vec<String>,
+  pub hunks: Vec<Hunk>,
+  pub added: usize,
+  pub removed: usize,
+  /// Hunks left out by [`Diff::truncate`]
+  pub omitted_hunks: usize,
+}
+
+/// A unified diff parsed per file, as produced by `git diff` or plain `diff -u`
+#[derive(Debug, Clone, Default)]
+pub struct Diff {
+  pub files: Vec<FileDiff>,
+}
+
+impl Diff {
+  pub fn parse(raw: &str) -> Self {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let lines: Vec<&str> = raw.lines().collect();
+    // Lines still expected in the current hunk, as (old, new)
+    let mut remaining = (0usize, 0usize);
+
+    for (i, line) in lines.iter().enumerate() {
+      // `\ No newline at end of file` may follow the last line of a hunk
+      let continues_hunk = remaining != (0, 0) || line.starts_with('\\');
+      if continues_hunk
+        && let Some(file) = files.last_mut()
+        && let Some(hunk) = file.hunks.last_mut()
+      {
+        hunk.lines.push(line.to_string());
+        match line.chars().next() {
+          Some('+') => {
+            file.added += 1;
+            remaining.1 = remaining.1.saturating_sub(1);
+          }
+          Some('-') => {
+            file.removed += 1;
+            remaining.0 = remaining.0.saturating_sub(1);
+          }
+          Some('\\') => {}
+          _ => {
+            remaining.0 = remaining.0.saturating_sub(1);
+            remaining.1 = remaining.1.saturating_sub(1);
+          }
+        }
+        continue;
+      }
+
+      if let Some(paths) = line.strip_prefix("diff --git ") {
+        let path = paths
+          .rsplit_once(" b/")
+          .map(|(_, path)| path.to_string())
+          .unwrap_or_default();
+        let mut file = FileDiff::new(path);
+        file.header.push(line.to_string());
+        files.push(file);
+        continue;
+      }
+
+      // Plain unified diffs have no `diff --git` line, a new file starts at `---` + `+++`
+      let starts_plain_file = line.starts_with("--- ")
+        && lines
+          .get(i + 1)
+          .is_some_and(|next| next.starts_with("+++ "))
+        && files.last().is_none_or(|file| !file.hunks.is_empty());
+      if starts_plain_file {
+        files.push(FileDiff::new(String::new()));
+      }
+
+      let Some(file) = files.last_mut() else {
+        continue;
+      };
+
+      if let Some(header) = line.strip_prefix("@@ ") {
+        let (old, new) = parse_hunk_range(header);
+        remaining = (old.1, new.1);
+        file.hunks.push(Hunk {
+          header: line.to_string(),
+          old_start: old.0,
+          new_start: new.0,
+          lines: Vec::new(),
+        });
+        continue;
+      }
+
+      file.header.push(line.to_string());
+      file.parse_header_line(line);
+    }
+
+    Self { files }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.files.is_empty()
+  }
+
+  /// Drop the files matching one of the `--ignore` patterns. They follow git pathspecs like the
+  /// ignore list of the staged diff: a path matches itself and everything below it, and
+  /// wildcards also match `/`.
+  pub fn ignore(&mut self, patterns: &[&str]) {
+    let globs: Vec<GlobMatcher> = patterns
+      .iter()
+      .filter(|pattern| pattern.contains(['*', '?', '[']))
+      .filter_map(|pattern| Glob::new(pattern).ok())
+      .map(|glob| glob.compile_matcher())
+      .collect();
+
+    self.files.retain(|file| {
+      let path = file.path.as_str();
+      let literal = patterns.iter().any(|pattern| {
+        let pattern = pattern.trim_end_matches('/');
+        path == pattern
+          || path
+            .strip_prefix(pattern)
+            .is_some_and(|rest| rest.starts_with('/'))
+      });
+      !literal && !globs.iter().any(|glob| glob.is_match(path))
+    });
+  }
+
+  pub fn paths(&self) -> Vec<String> {
+    self.files.iter().map(|file| file.path.clone()).collect()
+  }
+
+  pub fn added(&self) -> usize {
+    self.files.iter().map(|file| file.added).sum()
+  }
+
+  pub fn removed(&self) -> usize {
+    self.files.iter().map(|file| file.removed).sum()
+  }
+
+  /// Render the diff back into unified diff text
+  pub fn render(&self) -> String {
+    self.files.iter().map(FileDiff::render).collect()
+  }
+
+  /// Leave out hunks until the rendered diff fits in `max_chars`, skipping hunks too large for
+  /// the remaining space so smaller changes later in the diff are kept. Every file keeps its
+  /// header and counts, so the stat still lists all changes. Returns the number of left out
+  /// hunks.
+  pub fn truncate(&mut self, max_chars: usize) -> usize {
+    let hunk_size = |hunk: &Hunk| {
+      hunk.header.len() + 1 + hunk.lines.iter().map(|line| line.len() + 1).sum::<usize>()
+    };
+
+    let mut size = 0;
+    let mut omitted = 0;
+    for file in &mut self.files {
+      size += file.header.iter().map(|line| line.len() + 1).sum::<usize>();
+      let total: usize = file.hunks.iter().map(hunk_size).sum();
+      if size + total <= max_chars {
+        size += total;
+        continue;
+      }
+
+      // Some hunks are left out, so keep room for the line saying how many
+      size += omitted_hunks_line(file.hunks.len()).len();
+      let hunks = std::mem::take(&mut file.hunks);
+      for hunk in hunks {
+        let hunk_size = hunk_size(&hunk);
+        if size + hunk_size <= max_chars {
+          size += hunk_size;
+          file.hunks.push(hunk);
+        } else {
+          file.omitted_hunks += 1;
+          omitted += 1;
+        }
+      }
+    }
+
+    omitted
+  }
+
+  /// A compact `git diff --stat`-like summary with the change kind of every file
+  pub fn stat(&self) -> String {
+    let mut result = String::new();
+    let width = self
+      .files
+      .iter()
+      .map(|file| file.display_path().len())
+      .max()
+      .unwrap_or_default();
+    // Scale the bars like git does so the largest file fits in 40 columns
+    let max_changes = self
+      .files
+      .iter()
+      .map(|file| file.added + file.removed)
+      .max()
+      .unwrap_or_default();
+    let scale = |count: usize| -> usize {
+      if max_changes <= 40 || count == 0 {
+        count
+      } else {
+        (count * 40 / max_changes).max(1)
+      }
+    };
+
+    for file in &self.files {
+      let changes = if file.binary {
+        "Bin".to_string()
+      } else {
+        format!(
+          "{} {}{}",
+          file.added + file.removed,
+          "+".repeat(scale(file.added)),
+          "-".repeat(scale(file.removed))
+        )
+        .trim_end()
+        .to_string()
+      };
+      result.push_str(&format!(
+        "{} {:width$} | {}\n",
+        file.kind.letter(),
+        file.display_path(),
+        changes
+      ));
+    }
+
+    result.push_str(&format!(
+      "{} files changed, {} insertions(+), {} deletions(-)\n",
+      self.files.len(),
+      self.added(),
+      self.removed()
+    ));
+
+    result
+  }
+}
+
+impl FileDiff {
+  fn new(path: String) -> Self {
+    Self {
+      path,
+      old_path: None,
+      kind: ChangeKind::Modified,
+      binary: false,
+      header: Vec::new(),
+      hunks: Vec::new(),
+      added: 0,
+      removed: 0,
+      omitted_hunks: 0,
+    }
+  }
+
+  fn parse_header_line(&mut self, line: &str) {
+    if line.starts_with("new file mode") {
+      self.kind = ChangeKind::Added;
+    } else if line.starts_with("deleted file mode") {
+      self.kind = ChangeKind::Deleted;
+    } else if let Some(from) = line.strip_prefix("rename from ") {
+      self.kind = ChangeKind::Renamed;
+      self.old_path = Some(from.to_string());
+    } else if let Some(to) = line.strip_prefix("rename to ") {
+      self.path = to.to_string();
+    } else if let Some(from) = line.strip_prefix("copy from ") {
+      self.kind = ChangeKind::Copied;
+      self.old_path = Some(from.to_string());
+    } else if let Some(to) = line.strip_prefix("copy to ") {
+      self.path = to.to_string();
+    } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+      self.binary = true;
+    } else if let Some(path) = line.strip_prefix("--- ") {
+      match strip_diff_path(path, "a/") {
+        None => self.kind = ChangeKind::Added,
+        Some(path) if self.path.is_empty() => self.path = path,
+        Some(_) => {}
+      }
+    } else if let Some(path) = line.strip_prefix("+++ ") {
+      match strip_diff_path(path, "b/") {
+        None => self.kind = ChangeKind::Deleted,
+        Some(path) => self.path = path,
+      }
+    }
+  }
+
+  /// The path as shown in the stat, `old => new` for renames and copies
+  pub fn display_path(&self) -> String {
+    match &self.old_path {
+      Some(old_path) => format!("{} => {}", old_path, self.path),
+      None => self.path.clone(),
+    }
+  }
+
+  pub fn render(&self) -> String {
+    let mut result = String::new();
+    for line in &self.header {
+      result.push_str(line);
+      result.push('\n');
+    }
+    for hunk in &self.hunks {
+      result.push_str(&hunk.header);
+      result.push('\n');
+      for line in &hunk.lines {
+        result.push_str(line);
+        result.push('\n');
+      }
+    }
+    if self.omitted_hunks > 0 {
+      result.push_str(&omitted_hunks_line(self.omitted_hunks));
+    }
+
+    result
+  }
+}
+
+fn omitted_hunks_line(count: usize) -> String {
+  format!("... {count} more hunks omitted to fit the context window\n")
+}
+
+/// Strip the `a/` or `b/` prefix and any trailing timestamp; `None` for `/dev/null`
+fn strip_diff_path(path: &str, prefix: &str) -> Option<String> {
+  let path = path.split('\t').next().unwrap_or_default().trim_end();
+  if path == "/dev/null" {
+    return None;
+  }
+
+  Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// Parse `-a,b +c,d @@` into `((a, b), (c, d))`, a missing count defaults to 1
+fn parse_hunk_range(header: &str) -> ((usize, usize), (usize, usize)) {
+  let mut parts = header.split_whitespace();
+  let parse = |part: Option<&str>, sign: char| -> (usize, usize) {
+    let Some(range) = part.and_then(|part| part.strip_prefix(sign)) else {
+      return (0, 0);
+    };
+    let mut numbers = range.splitn(2, ',');
+    let start = numbers.next().and_then(|n| n.parse().ok()).unwrap_or(0);
+    let count = numbers.next().and_then(|n| n.parse().ok()).unwrap_or(1);
+    (start, count)
+  };
+
+  let old = parse(parts.next(), '-');
+  let new = parse(parts.next(), '+');
+  (old, new)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn renames_keep_both_paths() {
+    let diff = Diff::parse(
+      "diff --git a/src/old.rs b/src/new.rs\n\
+       similarity index 100%\n\
+       rename from src/old.rs\n\
+       rename to src/new.rs\n",
+    );
+
+    let file = &diff.files[0];
+    assert_eq!(file.kind, ChangeKind::Renamed);
+    assert_eq!(file.old_path.as_deref(), Some("src/old.rs"));
+    assert_eq!(file.path, "src/new.rs");
+    assert_eq!(file.display_path(), "src/old.rs => src/new.rs");
+    assert!(file.hunks.is_empty());
+  }
+
+  #[test]
+  fn binary_files_are_marked() {
+    let diff = Diff::parse(
+      "diff --git a/logo.png b/logo.png\n\
+       new file mode 100644\n\
+       index 0000000..1234567\n\
+       Binary files /dev/null and b/logo.png differ\n",
+    );
+
+    let file = &diff.files[0];
+    assert!(file.binary);
+    assert_eq!(file.kind, ChangeKind::Added);
+    assert_eq!(file.path, "logo.png");
+    assert!(diff.stat().starts_with("A logo.png | Bin\n"));
+  }
+
+  #[test]
+  fn mode_changes_have_no_hunks() {
+    let raw = "diff --git a/run.sh b/run.sh\n\
+               old mode 100644\n\
+               new mode 100755\n";
+    let diff = Diff::parse(raw);
+
+    let file = &diff.files[0];
+    assert_eq!(file.kind, ChangeKind::Modified);
+    assert_eq!(file.path, "run.sh");
+    assert!(file.hunks.is_empty());
+    assert_eq!((file.added, file.removed), (0, 0));
+    assert_eq!(diff.render(), raw);
+  }
+
+  #[test]
+  fn no_newline_markers_stay_in_their_hunk() {
+    let raw = "diff --git a/a.txt b/a.txt\n\
+               --- a/a.txt\n\
+               +++ b/a.txt\n\
+               @@ -1 +1 @@\n\
+               -old\n\
+               \\ No newline at end of file\n\
+               +new\n\
+               \\ No newline at end of file\n\
+               diff --git a/b.txt b/b.txt\n\
+               --- a/b.txt\n\
+               +++ b/b.txt\n\
+               @@ -1 +1,2 @@\n \
+               b\n\
+               +c\n";
+    let diff = Diff::parse(raw);
+
+    assert_eq!(diff.paths(), vec!["a.txt", "b.txt"]);
+    let first = &diff.files[0];
+    assert_eq!(first.hunks[0].lines.len(), 4);
+    assert_eq!((first.added, first.removed), (1, 1));
+    assert_eq!((diff.files[1].added, diff.files[1].removed), (1, 0));
+    assert_eq!(diff.render(), raw);
+  }
+
+  #[test]
+  fn plain_unified_diffs_are_split_per_file() {
+    let diff = Diff::parse(
+      "--- a.txt\t2024-01-01 00:00:00\n\
+       +++ a.txt\t2024-01-02 00:00:00\n\
+       @@ -1 +1 @@\n\
+       -a\n\
+       +b\n\
+       --- /dev/null\n\
+       +++ b.txt\n\
+       @@ -0,0 +1 @@\n\
+       +b\n",
+    );
+
+    assert_eq!(diff.paths(), vec!["a.txt", "b.txt"]);
+    assert_eq!(diff.files[0].kind, ChangeKind::Modified);
+    assert_eq!(diff.files[1].kind, ChangeKind::Added);
+  }
+
+  #[test]
+  fn ignored_paths_are_dropped_like_git_pathspecs() {
+    let raw: String = [
+      "Cargo.lock",
+      "crates/core/Cargo.lock",
+      "vendor/lib/a.rs",
+      "vendored.rs",
+      "web/package-lock.json",
+      "src/main.rs",
+    ]
+    .iter()
+    .map(|path| format!("diff --git a/{path} b/{path}\n@@ -1 +1 @@\n-a\n+b\n"))
+    .collect();
+    let mut diff = Diff::parse(&raw);
+
+    diff.ignore(&["Cargo.lock", "vendor/", "*package-lock.json"]);
+
+    assert_eq!(
+      diff.paths(),
+      ["crates/core/Cargo.lock", "vendored.rs", "src/main.rs"]
+    );
+  }
+
+  fn hunk(start: usize, lines: usize) -> String {
+    let body: String = (0..lines).map(|i| format!("+line {i}\n")).collect();
+    format!("@@ -{start},0 +{start},{lines} @@\n{body}")
+  }
+
+  #[test]
+  fn truncate_stays_within_the_limit_and_counts_omitted_hunks() {
+    let raw = format!(
+      "diff --git a/large.txt b/large.txt\n\
+       --- a/large.txt\n\
+       +++ b/large.txt\n\
+       {}{}{}\
+       diff --git a/small.txt b/small.txt\n\
+       --- a/small.txt\n\
+       +++ b/small.txt\n\
+       {}",
+      hunk(1, 2),
+      hunk(10, 200),
+      hunk(300, 2),
+      hunk(1, 1),
+    );
+    let full = Diff::parse(&raw);
+    assert_eq!(full.render(), raw);
+
+    for max_chars in [300, 400, 500, 1000] {
+      let mut diff = full.clone();
+      let omitted = diff.truncate(max_chars);
+      let rendered = diff.render();
+
+      assert!(rendered.len() <= max_chars, "{max_chars}: {rendered}");
+      let kept: usize = diff.files.iter().map(|file| file.hunks.len()).sum();
+      assert_eq!(kept + omitted, 4, "{max_chars}");
+      let reported: usize = diff.files.iter().map(|file| file.omitted_hunks).sum();
+      assert_eq!(reported, omitted, "{max_chars}");
+      assert_eq!(
+        (diff.added(), diff.removed()),
+        (full.added(), full.removed())
+      );
+    }
+
+    let mut diff = full.clone();
+    assert_eq!(diff.truncate(500), 1);
+    let rendered = diff.render();
+    assert!(rendered.contains("... 1 more hunks omitted"));
+    assert!(rendered.contains("+line 0\n"), "small hunks are kept");
+    assert!(!rendered.contains("+line 199\n"));
+
+    let mut diff = full.clone();
+    assert_eq!(diff.truncate(raw.len()), 0);
+    assert_eq!(diff.render(), raw);
+  }
+}
@@ -1,11 +1,19 @@
+use crate::auth::AuthError;
+use crate::cache::CacheError;
+use crate::classify::ClassifyError;
 use crate::client::{ClientError, CreateClientError};
+use crate::config::ConfigError;
 use crate::git;
-use std::env;
+use crate::issues::IssuesError;
+use crate::message::MessageError;
+use crate::scopes::ScopesError;
+use crate::trailers::TrailersError;
 
 #[derive(Debug, thiserror::Error)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
-  #[error("ANTHROPIC_API_KEY environment variable not found")]
-  CouldNotFindAnthropicKey(#[from] env::VarError),
+  #[error(transparent)]
+  AuthError(#[from] AuthError),
   #[error(transparent)]
   GitError(#[from] git::GitError),
   #[error(transparent)]
@@ -14,6 +22,20 @@ pub enum Error {
   ClientError(#[from] ClientError),
   #[error(transparent)]
   CreateClientError(#[from] CreateClientError),
+  #[error(transparent)]
+  ConfigError(#[from] ConfigError),
+  #[error(transparent)]
+  IssuesError(#[from] IssuesError),
+  #[error(transparent)]
+  ScopesError(#[from] ScopesError),
+  #[error(transparent)]
+  ClassifyError(#[from] ClassifyError),
+  #[error(transparent)]
+  TrailersError(#[from] TrailersError),
+  #[error(transparent)]
+  CacheError(#[from] CacheError),
+  #[error(transparent)]
+  MessageError(#[from] MessageError),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
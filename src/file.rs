@@ -14,3 +14,16 @@ pub async fn read_file<T: AsRef<str>>(path: T) -> Result<String> {
 pub fn file_exists<T: AsRef<str>>(path: T) -> bool {
   Path::new(path.as_ref()).exists()
 }
+
+/// Read the file at `path`, or stdin when `path` is `-`
+pub async fn read_file_or_stdin<T: AsRef<str>>(path: T) -> Result<String> {
+  if path.as_ref() != "-" {
+    return read_file(path).await;
+  }
+
+  let mut content = String::new();
+  let mut reader = BufReader::new(tokio::io::stdin());
+  reader.read_to_string(&mut content).await?;
+
+  Ok(content)
+}
@@ -0,0 +1,252 @@
+use crate::client::{
+  AIClient, ClientError, GenerateResponseResult, ReasoningEffort, ResponseFuture, Result, UsageInfo,
+};
+use crate::http;
+use crate::message::JsonSchema;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiRequest {
+  system_instruction: Content,
+  contents: Vec<Content>,
+  generation_config: GenerationConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Content {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  role: Option<String>,
+  #[serde(default)]
+  parts: Vec<Part>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Part {
+  #[serde(default)]
+  text: String,
+  /// Set on thought summaries of thinking models, which are not part of the answer
+  #[serde(default, skip_serializing)]
+  thought: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+  /// Includes the thinking tokens of thinking models
+  max_output_tokens: u32,
+  temperature: f32,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  thinking_config: Option<ThinkingConfig>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  response_mime_type: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  response_json_schema: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThinkingConfig {
+  thinking_budget: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiResponse {
+  #[serde(default)]
+  candidates: Vec<Candidate>,
+  usage_metadata: GeminiUsage,
+}
+
+#[derive(Debug, Deserialize)]
+struct Candidate {
+  content: Option<Content>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiUsage {
+  #[serde(default)]
+  prompt_token_count: u32,
+  /// Excludes the thinking tokens
+  #[serde(default)]
+  candidates_token_count: u32,
+  #[serde(default)]
+  thoughts_token_count: u32,
+  #[serde(default)]
+  total_token_count: u32,
+  /// Part of `prompt_token_count` that was served from the context cache
+  #[serde(default)]
+  cached_content_token_count: u32,
+}
+
+/// API root including the version, overridable with `GEMINI_BASE_URL`
+pub const GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+pub struct GeminiClient {
+  client: Client,
+  api_key: String,
+  base_url: String,
+  model: String,
+  max_tokens: u32,
+  temperature: f32,
+  reasoning_effort: Option<ReasoningEffort>,
+}
+
+/// Gemini 2.5 and later models think before answering, by default with a dynamic budget
+fn is_thinking_model(model: &str) -> bool {
+  model.starts_with("gemini-2.5") || model.starts_with("gemini-3")
+}
+
+impl GeminiClient {
+  pub fn new(api_key: String, client: Client) -> Self {
+    Self {
+      client,
+      api_key,
+      base_url: GEMINI_BASE_URL.to_string(),
+      model: "gemini-2.5-flash".to_string(),
+      max_tokens: 500,
+      temperature: 0.7,
+      reasoning_effort: None,
+    }
+  }
+
+  /// Send requests to another API root, e.g. a proxy or a local stand-in
+  pub fn with_base_url(self, base_url: &str) -> Self {
+    Self {
+      base_url: base_url.trim_end_matches('/').to_string(),
+      ..self
+    }
+  }
+}
+
+impl AIClient for GeminiClient {
+  fn set_model(&mut self, model: String) {
+    self.model = model;
+  }
+
+  fn set_max_tokens(&mut self, max_tokens: u32) {
+    self.max_tokens = max_tokens;
+  }
+
+  fn set_temperature(&mut self, temperature: f32) {
+    self.temperature = temperature;
+  }
+
+  fn set_reasoning_effort(&mut self, effort: ReasoningEffort) -> bool {
+    if !is_thinking_model(&self.model) {
+      return false;
+    }
+
+    self.reasoning_effort = Some(effort);
+    true
+  }
+
+  fn generate_response(&self, system_prompt: String, user_prompt: String) -> ResponseFuture<'_> {
+    Box::pin(self.generate_response_impl(system_prompt, user_prompt, None))
+  }
+
+  fn generate_structured_response(
+    &self,
+    system_prompt: String,
+    user_prompt: String,
+    schema: JsonSchema,
+  ) -> ResponseFuture<'_> {
+    Box::pin(self.generate_response_impl(system_prompt, user_prompt, Some(schema)))
+  }
+}
+
+impl GeminiClient {
+  /// With a schema, the response is constrained to JSON via `responseJsonSchema`
+  async fn generate_response_impl(
+    &self,
+    system_prompt: String,
+    user_prompt: String,
+    schema: Option<JsonSchema>,
+  ) -> Result<GenerateResponseResult> {
+    let text_content = |role: Option<&str>, text: String| Content {
+      role: role.map(|role| role.to_string()),
+      parts: vec![Part {
+        text,
+        thought: false,
+      }],
+    };
+
+    // Thinking tokens count against `maxOutputTokens`, reserve them on top of the answer
+    let max_output_tokens = if is_thinking_model(&self.model) {
+      let effort = self.reasoning_effort.unwrap_or(ReasoningEffort::Medium);
+      self.max_tokens + effort.budget_tokens()
+    } else {
+      self.max_tokens
+    };
+
+    let request = GeminiRequest {
+      system_instruction: text_content(None, system_prompt),
+      contents: vec![text_content(Some("user"), user_prompt)],
+      generation_config: GenerationConfig {
+        max_output_tokens,
+        temperature: self.temperature,
+        thinking_config: self.reasoning_effort.map(|effort| ThinkingConfig {
+          thinking_budget: effort.budget_tokens(),
+        }),
+        response_mime_type: schema.as_ref().map(|_| "application/json".to_string()),
+        response_json_schema: schema.map(|schema| schema.schema),
+      },
+    };
+
+    let response = self
+      .client
+      .post(format!(
+        "{}/models/{}:generateContent",
+        self.base_url, self.model
+      ))
+      .header("Content-Type", "application/json")
+      .header("x-goog-api-key", &self.api_key)
+      .json(&request)
+      .send()
+      .await
+      .map_err(http::send_error)?;
+
+    if !response.status().is_success() {
+      let error_text = response.text().await.unwrap_or_default();
+      return Err(ClientError::RequestFailed(error_text));
+    }
+
+    let api_response: GeminiResponse = response
+      .json()
+      .await
+      .map_err(|_| ClientError::FailedToParseResponse)?;
+
+    let message = api_response
+      .candidates
+      .first()
+      .and_then(|candidate| candidate.content.as_ref())
+      .map(|content| {
+        content
+          .parts
+          .iter()
+          .filter(|part| !part.thought)
+          .map(|part| part.text.as_str())
+          .collect::<String>()
+      })
+      .unwrap_or_default()
+      .trim()
+      .to_string();
+
+    let usage = api_response.usage_metadata;
+    let usage = UsageInfo {
+      input_tokens: usage
+        .prompt_token_count
+        .saturating_sub(usage.cached_content_token_count),
+      output_tokens: usage.candidates_token_count + usage.thoughts_token_count,
+      total_tokens: usage.total_token_count,
+      cache_creation_input_tokens: 0,
+      cache_read_input_tokens: usage.cached_content_token_count,
+      reasoning_tokens: usage.thoughts_token_count,
+    };
+
+    Ok(GenerateResponseResult { message, usage })
+  }
+}
@@ -1,3 +1,5 @@
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
 
 #[derive(Debug, thiserror::Error, Clone)]
@@ -6,13 +8,23 @@ pub enum GitError {
   NoStagedChanges,
   #[error("Failed to execute {0}")]
   FailedToExecuteCmd(String),
+  /// The output of git and its hooks is already shown, `code` is `None` when git was killed
+  #[error("Commit failed, see the git output above: {}", commit_status(*.code))]
+  CommitFailed { code: Option<i32> },
+}
+
+fn commit_status(code: Option<i32>) -> String {
+  match code {
+    Some(code) => format!("git commit exited with status {code}"),
+    None => "git commit was terminated by a signal".to_string(),
+  }
 }
 
 type Result<T> = std::result::Result<T, GitError>;
 
 pub async fn is_git_repo() -> bool {
   let output = Command::new("git")
-    .args(&["rev-parse", "--git-dir"])
+    .args(["rev-parse", "--git-dir"])
     .current_dir(".")
     .output()
     .await;
@@ -46,16 +58,18 @@ pub async fn get_staged_diff(ignore_list: &mut Vec<String>) -> Result<String> {
   Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
-pub async fn get_modified_files() -> Result<Vec<String>> {
+/// Paths of all staged files, including the ones excluded from the diff by the ignore list
+pub async fn get_staged_files() -> Result<Vec<String>> {
+  let err = || GitError::FailedToExecuteCmd(String::from("git diff --staged --name-only"));
   let output = Command::new("git")
-    .args(&["diff", "--name-only", "--staged"])
+    .args(["diff", "--staged", "--name-only"])
     .current_dir(".")
     .output()
     .await
-    .map_err(|_| GitError::FailedToExecuteCmd(String::from("git diff --name-only --staged")))?;
+    .map_err(|_| err())?;
 
   if !output.status.success() {
-    return Err(GitError::NoStagedChanges);
+    return Err(err());
   }
 
   let files = String::from_utf8_lossy(&output.stdout)
@@ -69,7 +83,7 @@ pub async fn get_modified_files() -> Result<Vec<String>> {
 pub async fn get_recent_commits(count: usize) -> Result<Vec<String>> {
   let err = || GitError::FailedToExecuteCmd(String::from("git log"));
   let output = Command::new("git")
-    .args(&["log", "--oneline", "-n", &count.to_string()])
+    .args(["log", "--oneline", "-n", &count.to_string()])
     .current_dir(".")
     .output()
     .await
@@ -84,21 +98,39 @@ pub async fn get_recent_commits(count: usize) -> Result<Vec<String>> {
     .lines()
     .map(|line| {
       // Skip the commit hash and just get the message
-      line.splitn(2, ' ').nth(1).unwrap_or("").to_string()
+      line
+        .split_once(' ')
+        .map(|(_, msg)| msg)
+        .unwrap_or("")
+        .to_string()
     })
     .collect();
 
   Ok(commits)
 }
 
-pub async fn commit(message: &str) -> Result<()> {
-  let mut child = Command::new("git")
-    .args(&["commit", "-m", message, "-e"])
+pub async fn commit(message: &str, edit: bool, extra_args: &[String]) -> Result<()> {
+  let err = || GitError::FailedToExecuteCmd(String::from("git commit"));
+  let mut args: Vec<&str> = vec!["commit", "-m", message];
+  if edit {
+    args.push("-e");
+  }
+  args.extend(extra_args.iter().map(|arg| arg.as_str()));
+
+  // All streams stay attached to the terminal, so the editor keeps working and hook output
+  // shows up as it is written, whether the commit succeeds or not
+  let status = Command::new("git")
+    .args(&args)
     .current_dir(".")
-    .spawn()
-    .map_err(|_| GitError::FailedToExecuteCmd(String::from("git commit")))?;
+    .status()
+    .await
+    .map_err(|_| err())?;
 
-  let _ = child.wait().await;
+  if !status.success() {
+    return Err(GitError::CommitFailed {
+      code: status.code(),
+    });
+  }
 
   Ok(())
 }
@@ -106,7 +138,7 @@ pub async fn commit(message: &str) -> Result<()> {
 pub async fn get_branch_name() -> Result<String> {
   let err = || GitError::FailedToExecuteCmd(String::from("git branch --show-current"));
   let output = Command::new("git")
-    .args(&["branch", "--show-current"])
+    .args(["branch", "--show-current"])
     .current_dir(".")
     .output()
     .await
@@ -116,13 +148,105 @@ pub async fn get_branch_name() -> Result<String> {
     return Err(err());
   }
 
-  Ok(String::from_utf8_lossy(&output.stdout).to_string())
+  Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
 pub async fn get_git_root() -> Result<String> {
   let err = || GitError::FailedToExecuteCmd(String::from("git rev-parse --show-toplevel"));
   let output = Command::new("git")
-    .args(&["rev-parse", "--show-toplevel"])
+    .args(["rev-parse", "--show-toplevel"])
+    .current_dir(".")
+    .output()
+    .await
+    .map_err(|_| err())?;
+
+  if !output.status.success() {
+    return Err(err());
+  }
+
+  Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub async fn get_git_dir() -> Result<String> {
+  let err = || GitError::FailedToExecuteCmd(String::from("git rev-parse --absolute-git-dir"));
+  let output = Command::new("git")
+    .args(["rev-parse", "--absolute-git-dir"])
+    .current_dir(".")
+    .output()
+    .await
+    .map_err(|_| err())?;
+
+  if !output.status.success() {
+    return Err(err());
+  }
+
+  Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub async fn get_user_ident() -> Result<String> {
+  let err = || GitError::FailedToExecuteCmd(String::from("git var GIT_COMMITTER_IDENT"));
+  let output = Command::new("git")
+    .args(["var", "GIT_COMMITTER_IDENT"])
+    .current_dir(".")
+    .output()
+    .await
+    .map_err(|_| err())?;
+
+  if !output.status.success() {
+    return Err(err());
+  }
+
+  // The ident is followed by a timestamp and timezone, e.g. `Name <email> 1700000000 +0000`
+  let ident = String::from_utf8_lossy(&output.stdout).to_string();
+  let ident = match ident.rfind('>') {
+    Some(end) => ident[..=end].to_string(),
+    None => ident.trim().to_string(),
+  };
+
+  Ok(ident)
+}
+
+pub async fn interpret_trailers(message: &str, trailers: &[String]) -> Result<String> {
+  let err = || GitError::FailedToExecuteCmd(String::from("git interpret-trailers"));
+  let mut args: Vec<String> = vec!["interpret-trailers".to_string()];
+  for trailer in trailers {
+    args.push("--trailer".to_string());
+    args.push(trailer.clone());
+  }
+
+  let mut child = Command::new("git")
+    .args(&args)
+    .current_dir(".")
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .spawn()
+    .map_err(|_| err())?;
+
+  let mut stdin = child.stdin.take().ok_or_else(err)?;
+  stdin
+    .write_all(message.as_bytes())
+    .await
+    .map_err(|_| err())?;
+  drop(stdin);
+
+  let output = child.wait_with_output().await.map_err(|_| err())?;
+
+  if !output.status.success() {
+    return Err(err());
+  }
+
+  Ok(
+    String::from_utf8_lossy(&output.stdout)
+      .trim_end()
+      .to_string(),
+  )
+}
+
+/// Content of a blob, e.g. `HEAD:src/main.rs` or `:src/main.rs` for the staged version
+pub async fn show_blob(spec: &str) -> Result<String> {
+  let err = || GitError::FailedToExecuteCmd(format!("git show {spec}"));
+  let output = Command::new("git")
+    .args(["show", spec])
     .current_dir(".")
     .output()
     .await
@@ -134,3 +258,36 @@ pub async fn get_git_root() -> Result<String> {
 
   Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
+
+/// List tracked files matching the glob pathspecs, relative to the repository root
+pub async fn list_files(patterns: &[&str]) -> Result<Vec<String>> {
+  let err = || GitError::FailedToExecuteCmd(String::from("git ls-files"));
+  let mut args: Vec<String> = vec![
+    "ls-files".to_string(),
+    "--full-name".to_string(),
+    "--".to_string(),
+  ];
+  args.extend(
+    patterns
+      .iter()
+      .map(|pattern| format!(":(top,glob){pattern}")),
+  );
+
+  let output = Command::new("git")
+    .args(&args)
+    .current_dir(".")
+    .output()
+    .await
+    .map_err(|_| err())?;
+
+  if !output.status.success() {
+    return Err(err());
+  }
+
+  let files = String::from_utf8_lossy(&output.stdout)
+    .lines()
+    .map(|s| s.to_string())
+    .collect();
+
+  Ok(files)
+}
@@ -0,0 +1,103 @@
+use crate::client::ClientError;
+use crate::config::NetworkConfig;
+use reqwest::{Certificate, Client, Identity, NoProxy, Proxy};
+use std::env;
+use std::error::Error as _;
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum HttpError {
+  #[error("Failed to read {0}")]
+  FailedToRead(String),
+  #[error("Invalid CA bundle {0}: {1}")]
+  InvalidCaBundle(String, reqwest::Error),
+  #[error("Invalid client certificate {0}: {1}")]
+  InvalidClientCert(String, reqwest::Error),
+  #[error("network.client_cert and network.client_key must be set together")]
+  IncompleteClientCert,
+  #[error("Invalid proxy URL {0}: {1}")]
+  InvalidProxy(String, reqwest::Error),
+  #[error("Failed to create HTTP client: {0}")]
+  FailedToBuild(reqwest::Error),
+}
+
+/// HTTP client shared by the API providers, configured for proxies, custom CAs, client
+/// certificates and the request timeout
+pub fn build_client(network: &NetworkConfig) -> Result<Client, HttpError> {
+  // Proxies are resolved below, so the config can take precedence over the environment
+  let mut builder = Client::builder().no_proxy();
+
+  if network.timeout_secs > 0 {
+    builder = builder.timeout(Duration::from_secs(network.timeout_secs));
+  }
+
+  if let Some(path) = &network.ca_bundle {
+    let pem = read(path)?;
+    let certificates = Certificate::from_pem_bundle(&pem)
+      .map_err(|e| HttpError::InvalidCaBundle(path.clone(), e))?;
+    for certificate in certificates {
+      builder = builder.add_root_certificate(certificate);
+    }
+  }
+
+  match (&network.client_cert, &network.client_key) {
+    (Some(cert_path), Some(key_path)) => {
+      let identity = Identity::from_pkcs8_pem(&read(cert_path)?, &read(key_path)?)
+        .map_err(|e| HttpError::InvalidClientCert(cert_path.clone(), e))?;
+      builder = builder.identity(identity);
+    }
+    (None, None) => {}
+    _ => return Err(HttpError::IncompleteClientCert),
+  }
+
+  let no_proxy = network
+    .no_proxy
+    .clone()
+    .or_else(|| env_var(&["NO_PROXY", "no_proxy"]));
+  let https_proxy = network
+    .proxy
+    .clone()
+    .or_else(|| env_var(&["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]));
+  let http_proxy = network
+    .proxy
+    .clone()
+    .or_else(|| env_var(&["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]));
+
+  if let Some(url) = https_proxy {
+    let proxy = Proxy::https(&url).map_err(|e| HttpError::InvalidProxy(url.clone(), e))?;
+    builder = builder.proxy(proxy.no_proxy(no_proxy.as_deref().and_then(NoProxy::from_string)));
+  }
+  if let Some(url) = http_proxy {
+    let proxy = Proxy::http(&url).map_err(|e| HttpError::InvalidProxy(url.clone(), e))?;
+    builder = builder.proxy(proxy.no_proxy(no_proxy.as_deref().and_then(NoProxy::from_string)));
+  }
+
+  builder.build().map_err(HttpError::FailedToBuild)
+}
+
+/// A failed request with its causes, e.g. the certificate or proxy error behind it
+pub fn send_error(error: reqwest::Error) -> ClientError {
+  let mut message = error.to_string();
+  let mut source = error.source();
+  while let Some(cause) = source {
+    message.push_str(&format!(": {cause}"));
+    source = cause.source();
+  }
+
+  if error.is_timeout() {
+    message.push_str(" (increase network.timeout_secs in the config for slow responses)");
+  }
+
+  ClientError::FailedToSend(message)
+}
+
+fn read(path: &str) -> Result<Vec<u8>, HttpError> {
+  std::fs::read(path).map_err(|_| HttpError::FailedToRead(path.to_string()))
+}
+
+/// The first of the variables that is set and not empty
+fn env_var(names: &[&str]) -> Option<String> {
+  names
+    .iter()
+    .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+}
@@ -0,0 +1,91 @@
+use crate::config::{IssueConvention, IssuesConfig};
+use regex::Regex;
+
+#[derive(Debug, thiserror::Error)]
+pub enum IssuesError {
+  #[error("Invalid branch pattern {0}: {1}")]
+  InvalidPattern(String, regex::Error),
+}
+
+/// Extract issue keys from the branch name using the configured patterns, keeping the order in
+/// which they appear and dropping duplicates.
+pub fn extract_issue_keys(
+  branch_name: &str,
+  config: &IssuesConfig,
+) -> Result<Vec<String>, IssuesError> {
+  let mut matches: Vec<(usize, String)> = Vec::new();
+
+  for pattern in &config.patterns {
+    let regex = Regex::new(pattern).map_err(|e| IssuesError::InvalidPattern(pattern.clone(), e))?;
+
+    for captures in regex.captures_iter(branch_name) {
+      let Some(m) = captures.get(1).or_else(|| captures.get(0)) else {
+        continue;
+      };
+      matches.push((m.start(), m.as_str().to_string()));
+    }
+  }
+
+  matches.sort_by_key(|(start, _)| *start);
+
+  let mut keys: Vec<String> = Vec::new();
+  for (_, key) in matches {
+    if !keys.contains(&key) {
+      keys.push(key);
+    }
+  }
+
+  Ok(keys)
+}
+
+/// Add the issue keys to the commit message according to the configured convention.
+pub fn apply_issue_keys(message: &str, keys: &[String], config: &IssuesConfig) -> String {
+  if keys.is_empty() {
+    return message.to_string();
+  }
+
+  match config.convention {
+    IssueConvention::None => message.to_string(),
+    IssueConvention::Prefix => {
+      let prefix = config.prefix_format.replace("{key}", &keys.join(", "));
+      if message.starts_with(&prefix) {
+        message.to_string()
+      } else {
+        format!("{prefix}{message}")
+      }
+    }
+    IssueConvention::Footer => {
+      let footers: Vec<String> = keys
+        .iter()
+        .map(|key| config.footer_format.replace("{key}", key))
+        .collect();
+
+      // Drop footers the model may have already written so they are not duplicated
+      let lines: Vec<&str> = message
+        .lines()
+        .filter(|line| !footers.iter().any(|footer| footer == line.trim()))
+        .collect();
+      let message = lines.join("\n").trim_end().to_string();
+
+      let last_paragraph = message.rsplit("\n\n").next().unwrap_or_default();
+      let ends_with_footers =
+        message.contains("\n\n") && last_paragraph.lines().all(is_footer_line);
+      let separator = if ends_with_footers { "\n" } else { "\n\n" };
+
+      format!("{message}{separator}{}", footers.join("\n"))
+    }
+  }
+}
+
+/// Whether a line looks like a conventional commit footer, e.g. `Refs: #1` or `Closes #1`
+fn is_footer_line(line: &str) -> bool {
+  if line.starts_with("BREAKING CHANGE: ") || line.starts_with("BREAKING-CHANGE: ") {
+    return true;
+  }
+
+  let Some((token, _)) = line.split_once(": ").or_else(|| line.split_once(" #")) else {
+    return false;
+  };
+
+  !token.is_empty() && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
@@ -1,15 +1,10 @@
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum LogLevel {
+  #[default]
   None,
   Verbose,
 }
 
-impl Default for LogLevel {
-  fn default() -> Self {
-    LogLevel::None
-  }
-}
-
 #[derive(Debug)]
 pub struct Logger {
   level: LogLevel,
@@ -1,36 +1,70 @@
 #![allow(dead_code)]
 
 use crate::client::UsageInfo;
+use crate::config::Config;
+use crate::diff::Diff;
 use crate::git::is_git_repo;
 use crate::logs::{LogLevel, Logger};
+use std::io::IsTerminal;
 
 mod analysis;
 mod anthropic;
 mod args;
+mod auth;
+mod breaking;
+mod cache;
+mod classify;
 mod client;
+mod codeowners;
+mod command;
+mod config;
+mod deps;
+mod diff;
 mod error;
 mod file;
+mod gemini;
 mod git;
+mod http;
+mod issues;
 mod logs;
+mod message;
+mod mock;
+mod models;
 mod openai;
 mod prompt;
+mod scopes;
+mod symbols;
+mod trailers;
+mod workspace;
 
 struct AppContext {
   branch_name: String,
-  scopes: String,
+  issue_keys: Vec<String>,
+  scopes: Vec<scopes::ScopeDefinition>,
+  scope_matches: Vec<scopes::ScopeMatch>,
   is_nx_repo: bool,
-  diff: String,
-  modified_files: Vec<String>,
+  affected_packages: Vec<String>,
+  code_owners: Vec<codeowners::OwnedFiles>,
+  diff: Diff,
+  file_categories: Vec<classify::Category>,
+  symbol_changes: Vec<symbols::FileSymbolChanges>,
+  breaking_changes: Vec<breaking::BreakingChange>,
+  dependency_changes: Vec<deps::DependencyChange>,
   recent_commits: Vec<String>,
 }
 
 async fn initialize_app() -> error::Result<(Logger, clap::ArgMatches)> {
-  if !is_git_repo().await {
+  let matches = args::get_matches();
+
+  // A diff file can come from any VCS, so only the staged index requires a git repository.
+  // API keys and the model catalog are managed outside of repositories.
+  let needs_repo = !matches.contains_id("diff-file")
+    && !matches!(matches.subcommand_name(), Some("auth" | "models"));
+  if needs_repo && !is_git_repo().await {
     eprintln!("not a git repository");
     std::process::exit(1);
   }
 
-  let matches = args::get_matches();
   let log_level = if matches.get_flag("verbose") {
     LogLevel::Verbose
   } else {
@@ -42,37 +76,75 @@ async fn initialize_app() -> error::Result<(Logger, clap::ArgMatches)> {
   Ok((logger, matches))
 }
 
+async fn get_root_dir(logger: &Logger, matches: &clap::ArgMatches) -> error::Result<String> {
+  let root_dir = logger
+    .exec_result_with_output("Getting git root directory", git::get_git_root, |root| {
+      format!("Git root: {}", root)
+    })
+    .await;
+
+  match root_dir {
+    Err(_)
+      if matches.contains_id("diff-file")
+        || matches!(matches.subcommand_name(), Some("auth" | "models")) =>
+    {
+      Ok(".".to_string())
+    }
+    root_dir => Ok(root_dir?),
+  }
+}
+
+async fn load_config(
+  logger: &Logger,
+  matches: &clap::ArgMatches,
+  root_dir: &str,
+) -> error::Result<Config> {
+  let config = logger
+    .exec_result("Loading config", || {
+      config::load_config(
+        matches.get_one::<String>("config").map(|s| s.as_str()),
+        root_dir,
+      )
+    })
+    .await?;
+
+  Ok(config)
+}
+
 async fn gather_git_context(
   logger: &Logger,
+  config: &Config,
+  root_dir: &str,
+  diff_file: Option<&str>,
   ignore_list: &mut Vec<String>,
 ) -> error::Result<AppContext> {
-  let root_dir = logger
-    .exec_result_with_output(
-      "Getting git root directory",
-      || git::get_git_root(),
-      |root| format!("Git root: {}", root),
-    )
-    .await?;
-
   let branch_name = logger
     .exec_result_with_output(
       "Getting current branch name",
-      || git::get_branch_name(),
+      || async {
+        match diff_file {
+          // The patch may not belong to the current checkout, so the branch is only a hint
+          Some(_) => Ok(git::get_branch_name().await.unwrap_or_default()),
+          None => git::get_branch_name().await,
+        }
+      },
       |branch| format!("Branch: {}", branch),
     )
     .await?;
 
+  let issue_keys = logger.exec_sync_result_with_output(
+    "Extracting issue keys from branch name",
+    || issues::extract_issue_keys(&branch_name, &config.issues),
+    |keys| format!("Issue keys: {}", keys.join(", ")),
+  )?;
+
   let scopes = logger
-    .exec_with_output(
+    .exec_result_with_output(
       "Reading scopes file",
-      || async {
-        file::read_file(format!("{root_dir}/scopes.txt"))
-          .await
-          .unwrap_or_default()
-      },
-      |scopes| format!("Scopes found: {}", !scopes.is_empty()),
+      || scopes::load_scopes(root_dir),
+      |scopes| format!("Scopes found: {}", scopes.len()),
     )
-    .await;
+    .await?;
 
   let is_nx_repo = logger
     .exec_with_output(
@@ -82,35 +154,149 @@ async fn gather_git_context(
     )
     .await;
 
-  let diff = logger
-    .exec_result_with_output(
-      "Getting staged diff",
-      || git::get_staged_diff(ignore_list),
-      |d| format!("Diff length: {} characters", d.len()),
-    )
-    .await?;
+  let raw_diff = match diff_file {
+    Some(path) => {
+      logger
+        .exec_result_with_output(
+          "Reading diff file",
+          || file::read_file_or_stdin(path),
+          |d| format!("Diff length: {} characters", d.len()),
+        )
+        .await?
+    }
+    None => {
+      logger
+        .exec_result_with_output(
+          "Getting staged diff",
+          || git::get_staged_diff(ignore_list),
+          |d| format!("Diff length: {} characters", d.len()),
+        )
+        .await?
+    }
+  };
+
+  logger.log_step("Parsing diff");
+  let mut diff = Diff::parse(&raw_diff);
+  // The staged diff leaves out the ignored files already, a diff file still contains them
+  if diff_file.is_some() {
+    let patterns: Vec<&str> = ignore_list
+      .iter()
+      .filter_map(|item| item.strip_prefix(":!"))
+      .collect();
+    diff.ignore(&patterns);
+  }
+
+  let dependency_changes = match diff_file {
+    // Manifests and lockfiles are read from the staged index
+    Some(_) => Vec::new(),
+    None => {
+      logger
+        .exec_with_output(
+          "Detecting dependency changes",
+          || async {
+            let staged_files = git::get_staged_files().await.unwrap_or_default();
+            deps::detect_dependency_changes(&staged_files).await
+          },
+          |changes| {
+            format!(
+              "Dependency changes:\n{}",
+              deps::format_dependency_changes(changes)
+            )
+          },
+        )
+        .await
+    }
+  };
 
-  if diff.is_empty() {
+  // A lockfile-only commit has an empty diff since lockfiles are ignored by default
+  if diff.is_empty() && dependency_changes.is_empty() {
     eprintln!("no changes detected");
     std::process::exit(1);
   }
 
-  let modified_files = logger
-    .exec_result_with_output(
-      "Getting modified files",
-      || git::get_modified_files(),
-      |files| {
-        let mut output = format!("Modified files count: {}", files.len());
-        if !files.is_empty() {
-          output.push_str("\nModified files:");
-          for file in files {
-            output.push_str(&format!("\n  - {}", file));
-          }
+  let modified_files = diff.paths();
+  logger.log_output(&format!("Modified files count: {}", modified_files.len()));
+  logger.log_output(&format!("Diff summary:\n{}", diff.stat()));
+
+  let file_categories = logger.exec_sync_result_with_output(
+    "Classifying modified files",
+    || classify::Classifier::new(&config.classify).map(|classifier| classifier.classify(&diff)),
+    |categories| {
+      format!(
+        "File categories:\n{}",
+        classify::format_categories(categories)
+      )
+    },
+  )?;
+
+  let symbol_changes = match diff_file {
+    // Blob contents are only available for the staged index
+    Some(_) => Vec::new(),
+    None => {
+      logger
+        .exec_with_output(
+          "Extracting symbol changes",
+          || symbols::extract_symbol_changes(&diff),
+          |changes| {
+            format!(
+              "Symbol changes:\n{}",
+              symbols::format_symbol_changes(changes)
+            )
+          },
+        )
+        .await
+    }
+  };
+
+  let breaking_changes = if config.breaking.detect {
+    logger
+      .exec_with_output(
+        "Detecting breaking changes",
+        || breaking::detect_breaking_changes(&symbol_changes),
+        |changes| {
+          format!(
+            "Breaking changes:\n{}",
+            breaking::format_breaking_changes(changes)
+          )
+        },
+      )
+      .await
+  } else {
+    Vec::new()
+  };
+
+  let scope_matches = logger.exec_sync_result_with_output(
+    "Matching scopes against modified files",
+    || scopes::match_scopes(&scopes, &modified_files),
+    |matches| format!("Matching scopes: {}", matches.len()),
+  )?;
+
+  let affected_packages = logger
+    .exec_with_output(
+      "Detecting affected workspace packages",
+      || async {
+        let projects = workspace::detect_projects(root_dir).await;
+        workspace::affected_projects(&projects, &modified_files)
+      },
+      |packages| format!("Affected packages: {}", packages.join(", ")),
+    )
+    .await;
+
+  let code_owners = logger
+    .exec_with_output(
+      "Reading CODEOWNERS",
+      || async {
+        match codeowners::CodeOwners::load(root_dir).await {
+          Some(owners) => owners.owned_files(&modified_files),
+          None => Vec::new(),
         }
-        output
+      },
+      |owned| {
+        let owners: Vec<&str> = owned.iter().map(|o| o.owner.as_str()).collect();
+        format!("Code owners: {}", owners.join(", "))
       },
     )
-    .await?;
+    .await;
 
   let recent_commits = logger
     .exec_with_output(
@@ -136,17 +322,52 @@ async fn gather_git_context(
 
   Ok(AppContext {
     branch_name,
+    issue_keys,
     scopes,
+    scope_matches,
     is_nx_repo,
+    affected_packages,
+    code_owners,
     diff,
-    modified_files,
+    file_categories,
+    symbol_changes,
+    breaking_changes,
+    dependency_changes,
     recent_commits,
   })
 }
 
+/// Return the cached response for `key` if there is one, otherwise generate and cache it
+async fn generate_cached<F, Fut>(
+  logger: &Logger,
+  cache: Option<&cache::ResponseCache>,
+  key: &str,
+  generate: F,
+) -> client::Result<client::GenerateResponseResult>
+where
+  F: FnOnce() -> Fut,
+  Fut: std::future::Future<Output = client::Result<client::GenerateResponseResult>>,
+{
+  let Some(cache) = cache else {
+    return generate().await;
+  };
+
+  if let Some(response) = cache.get(key).await {
+    logger.log_output("Using cached response");
+    return Ok(response);
+  }
+
+  let response = generate().await?;
+  cache.put(key, &response).await;
+
+  Ok(response)
+}
+
 async fn process_with_ai(
   logger: &Logger,
   matches: &clap::ArgMatches,
+  config: &Config,
+  registry: &models::ModelRegistry,
   context: &AppContext,
 ) -> error::Result<(String, Option<UsageInfo>, UsageInfo)> {
   let client = logger.exec_sync_result_with_output(
@@ -155,22 +376,84 @@ async fn process_with_ai(
       client::create_client(
         matches.get_one::<String>("model").unwrap(),
         *matches.get_one::<u32>("max-tokens").unwrap(),
+        matches
+          .get_one::<String>("reasoning-effort")
+          .and_then(|effort| client::ReasoningEffort::parse(effort)),
+        &config.providers,
+        &config.network,
+        registry,
       )
     },
     |_| format!("Model: {}", matches.get_one::<String>("model").unwrap()),
-  )?;
+  );
+
+  // Explain where keys are looked up or what the model supports instead of printing the bare error
+  let client = match client {
+    Err(
+      e @ (client::CreateClientError::APIKeyError(_)
+      | client::CreateClientError::ReasoningNotSupported(_)
+      | client::CreateClientError::Model(_)),
+    ) => {
+      eprintln!("{e}");
+      std::process::exit(1);
+    }
+    client => client?,
+  };
+
+  let model = matches.get_one::<String>("model").unwrap();
+  let max_tokens = matches.get_one::<u32>("max-tokens").unwrap().to_string();
+  let reasoning_effort = matches
+    .get_one::<String>("reasoning-effort")
+    .cloned()
+    .unwrap_or_default();
+  let reasoning_budget =
+    client::ReasoningEffort::parse(&reasoning_effort).map_or(0, |effort| effort.budget_tokens());
+
+  let diff = fit_diff(
+    context,
+    model,
+    registry.find(model),
+    *matches.get_one::<u32>("max-tokens").unwrap(),
+    reasoning_budget,
+  )
+  .await?;
+
+  let response_cache = if config.cache.enabled && !matches.get_flag("no-cache") {
+    cache::ResponseCache::open(&config.cache).await.ok()
+  } else {
+    None
+  };
 
   let (analysis_message, analysis_usage) = if matches.get_flag("no-analysis") {
     logger.log_step("Skipping AI analysis (--no-analysis flag enabled)");
     (String::new(), None)
+  } else if context.diff.is_empty() {
+    logger.log_step("Skipping AI analysis (only ignored files changed)");
+    (String::new(), None)
   } else {
+    let (system_prompt, user_prompt) = analysis::get_analysis_prompts(&diff);
+    let key = cache::ResponseCache::key(&[
+      "analysis",
+      model,
+      &max_tokens,
+      &reasoning_effort,
+      &system_prompt,
+      &user_prompt,
+    ]);
+
     let analysis_response = logger.exec_result_with_output(
       "Analyzing changes with AI",
-      || analysis::analyze_changes_with_ai(client.as_ref(), &context.diff),
+      || {
+        generate_cached(logger, response_cache.as_ref(), &key, || {
+          client.generate_response(system_prompt, user_prompt)
+        })
+      },
       |resp| format!(
-        "Change analysis length: {} characters\nAnalysis usage - Input: {}, Output: {}, Total: {}\nAnalysis content:\n{}",
+        "Change analysis length: {} characters\nAnalysis usage - Input: {}, Cache write: {}, Cache read: {}, Output: {}, Total: {}\nAnalysis content:\n{}",
         resp.message.len(),
         resp.usage.input_tokens,
+        resp.usage.cache_creation_input_tokens,
+        resp.usage.cache_read_input_tokens,
         resp.usage.output_tokens,
         resp.usage.total_tokens,
         resp.message
@@ -182,88 +465,235 @@ async fn process_with_ai(
   let user_prompt = logger
     .exec_result_with_output(
       "Building user prompt",
-      || {
-        prompt::get_commit_user_prompt(
-          context.branch_name.clone(),
-          context.scopes.clone(),
-          context.is_nx_repo,
-          context.diff.clone(),
-          context.modified_files.clone(),
-          context.recent_commits.clone(),
-          analysis_message,
-        )
-      },
+      || prompt::get_commit_user_prompt(commit_prompt_data(context, diff, analysis_message)),
       |prompt| format!("User prompt length: {} characters", prompt.len()),
     )
     .await?;
 
+  let system_prompt = prompt::get_commit_system_prompt();
+  let generation_cache = response_cache.as_ref().filter(|_| config.cache.generation);
+  let key = cache::ResponseCache::key(&[
+    "generation",
+    model,
+    &max_tokens,
+    &reasoning_effort,
+    &system_prompt,
+    &user_prompt,
+  ]);
+
   let response = logger
     .exec_result("Generating commit message", || {
-      client.generate_response(prompt::get_commit_system_prompt(), user_prompt)
+      generate_cached(logger, generation_cache, &key, || {
+        client.generate_structured_response(
+          system_prompt,
+          user_prompt,
+          message::commit_message_schema(),
+        )
+      })
     })
     .await?;
 
-  Ok((response.message, analysis_usage, response.usage))
+  let commit_message = logger.exec_sync_result_with_output(
+    "Assembling commit message",
+    || message::CommitMessage::parse(&response.message),
+    |message| format!("Commit message fields:\n{:#?}", message),
+  )?;
+
+  Ok((commit_message.render(), analysis_usage, response.usage))
 }
 
-fn report_usage(logger: &Logger, analysis_usage: &Option<UsageInfo>, generation_usage: &UsageInfo) {
-  logger.log_output("--- Individual Usage ---");
-  if let Some(usage) = analysis_usage {
-    logger.log_output("Analysis:");
+fn commit_prompt_data(
+  context: &AppContext,
+  diff: Diff,
+  change_analysis: String,
+) -> prompt::CommitPromptData {
+  prompt::CommitPromptData {
+    branch_name: context.branch_name.clone(),
+    issue_keys: context.issue_keys.clone(),
+    scopes: scopes::format_scopes(&context.scopes),
+    scope_matches: scopes::format_scope_matches(&context.scope_matches),
+    is_nx_repo: context.is_nx_repo,
+    affected_packages: context.affected_packages.clone(),
+    code_owners: codeowners::format_owned_files(&context.code_owners),
+    diff,
+    file_categories: classify::format_categories(&context.file_categories),
+    symbol_changes: symbols::format_symbol_changes(&context.symbol_changes),
+    breaking_changes: breaking::format_breaking_changes(&context.breaking_changes),
+    dependency_changes: deps::format_dependency_changes(&context.dependency_changes),
+    recent_commits: context.recent_commits.clone(),
+    change_analysis,
+  }
+}
+
+/// The staged diff, trimmed to the context window of the model. The response, the instructions
+/// and the rest of the commit prompt are reserved first.
+async fn fit_diff(
+  context: &AppContext,
+  model: &str,
+  info: Option<&models::ModelInfo>,
+  max_tokens: u32,
+  reasoning_budget: u32,
+) -> error::Result<Diff> {
+  let mut diff = context.diff.clone();
+  let Some(info) = info else {
+    return Ok(diff);
+  };
+  let Some(budget) = info.input_budget(max_tokens.saturating_add(reasoning_budget)) else {
+    return Ok(diff);
+  };
+
+  let prompt_without_diff =
+    prompt::get_commit_user_prompt(commit_prompt_data(context, Diff::default(), String::new()))
+      .await?;
+  let reserved = models::estimate_tokens(&prompt::get_commit_system_prompt())
+    + models::estimate_tokens(&prompt_without_diff)
+    + models::estimate_tokens(&diff.stat());
+  let max_chars = budget.saturating_sub(reserved) as usize * models::CHARS_PER_TOKEN;
+
+  if diff.render().len() > max_chars {
+    let omitted = diff.truncate(max_chars);
+    println!(
+      "[OUTPUT] Warning: left out {omitted} hunks of the diff to fit the {} token context window of {model}",
+      info.context_window
+    );
+  }
+
+  Ok(diff)
+}
+
+async fn add_trailers(
+  logger: &Logger,
+  matches: &clap::ArgMatches,
+  config: &Config,
+  context: &AppContext,
+  commit_message: &str,
+) -> error::Result<String> {
+  let values = |id: &str| -> Vec<&str> {
+    matches
+      .get_many::<String>(id)
+      .map(|values| values.map(|s| s.as_str()).collect())
+      .unwrap_or_default()
+  };
+
+  let options = trailers::TrailerOptions {
+    signoff: matches.get_flag("signoff"),
+    co_authors: values("co-author"),
+    trailers: values("trailer"),
+    generated_by: (matches.get_flag("generated-by") || config.trailers.generated_by)
+      .then(|| matches.get_one::<String>("model").unwrap().as_str()),
+    code_owners: if config.codeowners.trailer {
+      context
+        .code_owners
+        .iter()
+        .map(|o| o.owner.as_str())
+        .collect()
+    } else {
+      Vec::new()
+    },
+  };
+
+  let trailer_lines = logger
+    .exec_result_with_output(
+      "Building trailers",
+      || trailers::build_trailers(&options, &config.trailers),
+      |lines| format!("Trailers count: {}", lines.len()),
+    )
+    .await?;
+
+  let commit_message = logger
+    .exec_result("Adding trailers", || {
+      trailers::apply_trailers(commit_message, &trailer_lines)
+    })
+    .await?;
+
+  Ok(commit_message)
+}
+
+fn report_usage(
+  logger: &Logger,
+  model_info: Option<&models::ModelInfo>,
+  analysis_usage: &Option<UsageInfo>,
+  generation_usage: &UsageInfo,
+) {
+  let log_usage = |usage: &UsageInfo| {
     logger.log_output(&format!("  Input tokens: {}", usage.input_tokens));
+    logger.log_output(&format!(
+      "  Cache write tokens: {}",
+      usage.cache_creation_input_tokens
+    ));
+    logger.log_output(&format!(
+      "  Cache read tokens: {}",
+      usage.cache_read_input_tokens
+    ));
     logger.log_output(&format!("  Output tokens: {}", usage.output_tokens));
+    logger.log_output(&format!("  Reasoning tokens: {}", usage.reasoning_tokens));
     logger.log_output(&format!("  Total tokens: {}", usage.total_tokens));
+  };
+
+  logger.log_output("--- Individual Usage ---");
+  if let Some(usage) = analysis_usage {
+    logger.log_output("Analysis:");
+    log_usage(usage);
   }
 
   logger.log_output("Commit Message Generation:");
-  logger.log_output(&format!(
-    "  Input tokens: {}",
-    generation_usage.input_tokens
-  ));
-  logger.log_output(&format!(
-    "  Output tokens: {}",
-    generation_usage.output_tokens
-  ));
-  logger.log_output(&format!(
-    "  Total tokens: {}",
-    generation_usage.total_tokens
-  ));
-
-  let (total_input, total_output, total_tokens) = if let Some(usage) = analysis_usage {
-    (
-      usage.input_tokens + generation_usage.input_tokens,
-      usage.output_tokens + generation_usage.output_tokens,
-      usage.total_tokens + generation_usage.total_tokens,
-    )
-  } else {
-    (
-      generation_usage.input_tokens,
-      generation_usage.output_tokens,
-      generation_usage.total_tokens,
-    )
-  };
+  log_usage(generation_usage);
+
+  let mut total = generation_usage.clone();
+  if let Some(usage) = analysis_usage {
+    total.input_tokens += usage.input_tokens;
+    total.output_tokens += usage.output_tokens;
+    total.total_tokens += usage.total_tokens;
+    total.cache_creation_input_tokens += usage.cache_creation_input_tokens;
+    total.cache_read_input_tokens += usage.cache_read_input_tokens;
+    total.reasoning_tokens += usage.reasoning_tokens;
+  }
 
   println!("\n--- Total Usage ---");
-  println!("  Input tokens: {}", total_input);
-  println!("  Output tokens: {}", total_output);
-  println!("  Total tokens: {}", total_tokens);
+  println!("  Input tokens: {}", total.input_tokens);
+  if total.cache_creation_input_tokens > 0 || total.cache_read_input_tokens > 0 {
+    println!(
+      "  Cache write tokens: {}",
+      total.cache_creation_input_tokens
+    );
+    println!("  Cache read tokens: {}", total.cache_read_input_tokens);
+  }
+  println!("  Output tokens: {}", total.output_tokens);
+  if total.reasoning_tokens > 0 {
+    println!("    of which reasoning: {}", total.reasoning_tokens);
+  }
+  println!("  Total tokens: {}", total.total_tokens);
+  if let Some(cost) = model_info.and_then(|info| info.cost(&total)) {
+    println!("  Estimated cost: ${cost:.4}");
+  }
 }
 
 async fn handle_commit_confirmation(
   logger: &Logger,
+  matches: &clap::ArgMatches,
   commit_message: &str,
-  is_dry_run: bool,
 ) -> error::Result<()> {
-  if !is_dry_run {
-    println!("\nCommit with this message? (y/N)");
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input)?;
+  let edit = !matches.get_flag("no-edit");
+  let commit_args: Vec<String> = matches
+    .get_many::<String>("commit-args")
+    .map(|args| args.cloned().collect())
+    .unwrap_or_default();
+
+  if !matches.get_flag("dry-run") {
+    let confirmed = if matches.get_flag("yes") {
+      true
+    } else {
+      println!("\nCommit with this message? (y/N)");
+      let mut input = String::new();
+      std::io::stdin().read_line(&mut input)?;
+      input.trim().to_lowercase() == "y"
+    };
 
-    if input.trim().to_lowercase() == "y" {
+    if confirmed {
       logger
         .exec_result_with_output(
           "Committing changes",
-          || git::commit(commit_message),
+          || git::commit(commit_message, edit, &commit_args),
           |_| "Commit successful".to_string(),
         )
         .await?;
@@ -275,10 +705,181 @@ async fn handle_commit_confirmation(
   Ok(())
 }
 
+async fn run_cache_command(
+  logger: &Logger,
+  matches: &clap::ArgMatches,
+  cache_matches: &clap::ArgMatches,
+) -> error::Result<()> {
+  let root_dir = get_root_dir(logger, matches).await?;
+  let config = load_config(logger, matches, &root_dir).await?;
+  let response_cache = cache::ResponseCache::open(&config.cache).await?;
+
+  if let Some(("clear", _)) = cache_matches.subcommand() {
+    let count = logger
+      .exec_result("Clearing cache", || response_cache.clear())
+      .await?;
+    println!("Removed {count} cached responses");
+  }
+
+  Ok(())
+}
+
+async fn run_models_command(
+  logger: &Logger,
+  matches: &clap::ArgMatches,
+  models_matches: &clap::ArgMatches,
+) -> error::Result<()> {
+  let root_dir = get_root_dir(logger, matches).await?;
+  let config = load_config(logger, matches, &root_dir).await?;
+  let registry = models::ModelRegistry::new(&config.models);
+  let provider = models_matches
+    .get_one::<String>("provider")
+    .map(|s| s.as_str());
+
+  let models = registry.models(provider);
+  let width = models
+    .iter()
+    .map(|model| model.name.len())
+    .max()
+    .unwrap_or_default()
+    .max("MODEL".len());
+  let count = |tokens: u32| {
+    if tokens == 0 {
+      "-".to_string()
+    } else {
+      tokens.to_string()
+    }
+  };
+  // Cents, or tenths of a cent for the cheaper cached rates
+  let price = |price: Option<f64>| {
+    price.map_or("-".to_string(), |price| {
+      let text = format!("{price:.3}");
+      text.strip_suffix('0').unwrap_or(&text).to_string()
+    })
+  };
+
+  let mut current_provider = None;
+  for model in models {
+    if current_provider != Some(&model.provider) {
+      if current_provider.is_some() {
+        println!();
+      }
+      println!("{}", model.provider);
+      println!(
+        "  {:width$}  {:>8}  {:>7}  {:>9}  {:>10}  {:>10}  SUPPORTS",
+        "MODEL", "CONTEXT", "OUTPUT", "INPUT $/M", "CACHED $/M", "OUTPUT $/M"
+      );
+      current_provider = Some(&model.provider);
+    }
+
+    let capabilities = model.capabilities;
+    let supports: Vec<&str> = [
+      (capabilities.temperature, "temperature"),
+      (capabilities.streaming, "streaming"),
+      (capabilities.structured_output, "structured output"),
+      (capabilities.caching, "caching"),
+      (capabilities.reasoning, "reasoning"),
+    ]
+    .into_iter()
+    .filter_map(|(supported, name)| supported.then_some(name))
+    .collect();
+
+    println!(
+      "  {:width$}  {:>8}  {:>7}  {:>9}  {:>10}  {:>10}  {}",
+      model.name,
+      count(model.context_window),
+      count(model.max_output_tokens),
+      price(model.pricing.map(|pricing| pricing.input)),
+      price(model.pricing.map(|pricing| pricing.cached_input)),
+      price(model.pricing.map(|pricing| pricing.output)),
+      supports.join(", ")
+    );
+  }
+
+  Ok(())
+}
+
+async fn run_auth_command(
+  logger: &Logger,
+  matches: &clap::ArgMatches,
+  auth_matches: &clap::ArgMatches,
+) -> error::Result<()> {
+  match auth_matches.subcommand() {
+    Some(("login", login_matches)) => {
+      let provider = login_matches.get_one::<String>("provider").unwrap();
+      let key = auth::read_key(provider)?;
+      logger.exec_sync_result("Storing API key in the keyring", || {
+        auth::keyring_set(provider, &key)
+      })?;
+      println!("Stored the API key for {provider} in the system keyring");
+
+      let var = auth::key_var(provider);
+      if std::env::var(var).is_ok_and(|key| !key.trim().is_empty()) {
+        println!("[OUTPUT] Warning: {var} is set and takes precedence over the keyring");
+      }
+    }
+    Some(("logout", logout_matches)) => {
+      let provider = logout_matches.get_one::<String>("provider").unwrap();
+      let removed = logger.exec_sync_result("Removing API key from the keyring", || {
+        auth::keyring_delete(provider)
+      })?;
+      if removed {
+        println!("Removed the API key for {provider} from the system keyring");
+      } else {
+        println!("No API key for {provider} is stored in the system keyring");
+      }
+    }
+    Some(("status", _)) => {
+      let root_dir = get_root_dir(logger, matches).await?;
+      let config = load_config(logger, matches, &root_dir).await?;
+
+      for provider in auth::PROVIDERS {
+        match auth::find_api_key(provider, &config.providers) {
+          Ok(Some((key, source))) => {
+            println!("{provider}: {} from {source}", auth::mask_key(&key))
+          }
+          Ok(None) => println!("{provider}: not set"),
+          Err(e) => println!("{provider}: {e}"),
+        }
+      }
+
+      if let Err(e) = auth::keyring_get(auth::PROVIDERS[0]) {
+        println!("[OUTPUT] Warning: {e}");
+      }
+    }
+    _ => {}
+  }
+
+  Ok(())
+}
+
 #[tokio::main]
 async fn main() -> error::Result<()> {
   let (logger, matches) = initialize_app().await?;
 
+  if let Some(("cache", cache_matches)) = matches.subcommand() {
+    return run_cache_command(&logger, &matches, cache_matches).await;
+  }
+  if let Some(("models", models_matches)) = matches.subcommand() {
+    return run_models_command(&logger, &matches, models_matches).await;
+  }
+  if let Some(("auth", auth_matches)) = matches.subcommand() {
+    let result = run_auth_command(&logger, &matches, auth_matches).await;
+    if let Err(error::Error::AuthError(e)) = &result {
+      eprintln!("{e}");
+      std::process::exit(1);
+    }
+    return result;
+  }
+
+  // Fail before any API call instead of blocking on a confirmation prompt that can't be answered
+  let needs_confirmation =
+    !matches.get_flag("dry-run") && !matches.get_flag("yes") && !matches.contains_id("diff-file");
+  if needs_confirmation && !std::io::stdin().is_terminal() {
+    eprintln!("stdin is not a terminal, pass --yes to commit without confirmation or --dry-run");
+    std::process::exit(1);
+  }
+
   let mut ignore_list: Vec<String> = matches
     .get_one::<String>("ignore")
     .map(|s| {
@@ -289,17 +890,75 @@ async fn main() -> error::Result<()> {
     })
     .unwrap_or_default();
 
-  let context = gather_git_context(&logger, &mut ignore_list).await?;
+  let diff_file = matches.get_one::<String>("diff-file").map(|s| s.as_str());
+
+  let root_dir = get_root_dir(&logger, &matches).await?;
 
+  let config = load_config(&logger, &matches, &root_dir).await?;
+
+  let context =
+    gather_git_context(&logger, &config, &root_dir, diff_file, &mut ignore_list).await?;
+
+  let registry = models::ModelRegistry::new(&config.models);
   let (commit_message, analysis_usage, generation_usage) =
-    process_with_ai(&logger, &matches, &context).await?;
+    process_with_ai(&logger, &matches, &config, &registry, &context).await?;
+
+  // The affected packages are offered as scopes too, and `deps` is suggested for dependency
+  // changes
+  let mut extra_scopes = context.affected_packages.clone();
+  if !context.dependency_changes.is_empty() {
+    extra_scopes.push("deps".to_string());
+  }
+  let (commit_message, scope_warnings) = scopes::validate_scope(
+    &commit_message,
+    &context.scopes,
+    &context.scope_matches,
+    &extra_scopes,
+    config.scopes.validation,
+  );
+  for warning in scope_warnings {
+    println!("[OUTPUT] Warning: {warning}");
+  }
+
+  // A false positive would change the release meaning of the commit, so this is opt-in
+  let commit_message = if config.breaking.mark {
+    breaking::apply_breaking_changes(&commit_message, &context.breaking_changes)
+  } else {
+    commit_message
+  };
+
+  let commit_message =
+    issues::apply_issue_keys(&commit_message, &context.issue_keys, &config.issues);
+
+  let commit_message = add_trailers(&logger, &matches, &config, &context, &commit_message).await?;
 
   println!("Generated commit message:\n");
   println!("{commit_message}");
 
-  report_usage(&logger, &analysis_usage, &generation_usage);
+  if !context.code_owners.is_empty() {
+    let owners: Vec<&str> = context
+      .code_owners
+      .iter()
+      .map(|o| o.owner.as_str())
+      .collect();
+    println!("\nOwners to review: {}", owners.join(", "));
+  }
 
-  handle_commit_confirmation(&logger, &commit_message, matches.get_flag("dry-run")).await?;
+  let model_info = registry.find(matches.get_one::<String>("model").unwrap());
+  report_usage(&logger, model_info, &analysis_usage, &generation_usage);
 
-  Ok(())
+  // There is no staged index to commit when the diff comes from a file
+  if diff_file.is_some() {
+    return Ok(());
+  }
+
+  let result = handle_commit_confirmation(&logger, &matches, &commit_message).await;
+
+  // Exit with git's own status so hooks failing in scripts are visible to the caller
+  if let Err(error::Error::GitError(error @ git::GitError::CommitFailed { code })) = &result {
+    eprintln!("{error}");
+    std::process::exit(code.unwrap_or(1));
+  }
+
+  result
 }
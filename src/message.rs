@@ -0,0 +1,275 @@
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+/// Conventional commit types the model may choose from
+pub const COMMIT_TYPES: [&str; 11] = [
+  "feat", "fix", "docs", "style", "refactor", "perf", "test", "chore", "ci", "build", "revert",
+];
+
+/// Name of the Anthropic tool and the OpenAI schema the model answers with
+pub const SCHEMA_NAME: &str = "commit_message";
+
+#[derive(Debug, thiserror::Error)]
+pub enum MessageError {
+  #[error("Response is not a valid commit message object: {0}")]
+  InvalidJson(String),
+  #[error("Commit message has an empty subject")]
+  EmptySubject,
+}
+
+/// A JSON schema the provider is asked to answer with, e.g. via a forced tool or `text.format`
+#[derive(Debug, Clone)]
+pub struct JsonSchema {
+  pub name: String,
+  pub description: String,
+  pub schema: Value,
+}
+
+/// The commit message as returned by the model, before it is assembled into text
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommitMessage {
+  pub r#type: String,
+  pub scope: Option<String>,
+  #[serde(default)]
+  pub breaking: bool,
+  pub subject: String,
+  #[serde(default)]
+  pub body: Vec<String>,
+  #[serde(default)]
+  pub footers: Vec<String>,
+  /// Subject prefix commonly used in the repository, e.g. `[Company]`
+  #[serde(default)]
+  pub prefix: Option<String>,
+}
+
+/// Schema of [`CommitMessage`]. Every property is required and optional ones are nullable, as
+/// strict structured output demands.
+pub fn commit_message_schema() -> JsonSchema {
+  let schema = json!({
+    "type": "object",
+    "properties": {
+      "type": {
+        "type": "string",
+        "enum": COMMIT_TYPES,
+        "description": "Conventional commit type"
+      },
+      "scope": {
+        "type": ["string", "null"],
+        "description": "Scope without parentheses, null when no scope applies"
+      },
+      "breaking": {
+        "type": "boolean",
+        "description": "Whether the change breaks backward compatibility"
+      },
+      "subject": {
+        "type": "string",
+        "description": "Imperative description after `type(scope): `, at most 72 characters, no trailing period"
+      },
+      "body": {
+        "type": "array",
+        "items": { "type": "string" },
+        "description": "Body lines, usually bullet points starting with `- `"
+      },
+      "footers": {
+        "type": "array",
+        "items": { "type": "string" },
+        "description": "Footer lines such as `BREAKING CHANGE: ...` or `Closes #123`"
+      },
+      "prefix": {
+        "type": ["string", "null"],
+        "description": "Subject prefix used by previous commits, e.g. `[Company]`, null when there is none"
+      }
+    },
+    "required": ["type", "scope", "breaking", "subject", "body", "footers", "prefix"],
+    "additionalProperties": false
+  });
+
+  JsonSchema {
+    name: SCHEMA_NAME.to_string(),
+    description: "Record the generated conventional commit message".to_string(),
+    schema,
+  }
+}
+
+impl CommitMessage {
+  /// Parse the JSON answer of the model. Text around the object, such as code fences, is
+  /// ignored for providers without native structured output.
+  pub fn parse(response: &str) -> Result<Self, MessageError> {
+    let start = response.find('{').unwrap_or_default();
+    let end = response
+      .rfind('}')
+      .map(|end| end + 1)
+      .unwrap_or(response.len());
+    let json = response.get(start..end).unwrap_or(response);
+
+    let message: Self =
+      serde_json::from_str(json).map_err(|e| MessageError::InvalidJson(e.to_string()))?;
+    message.repair()
+  }
+
+  /// Normalize the fields so the assembled message always has a valid conventional subject
+  fn repair(mut self) -> Result<Self, MessageError> {
+    self.r#type = self.r#type.trim().to_lowercase();
+    if !COMMIT_TYPES.contains(&self.r#type.as_str()) {
+      self.r#type = "chore".to_string();
+    }
+
+    self.scope = self
+      .scope
+      .map(|scope| {
+        scope
+          .trim()
+          .trim_matches(|c| c == '(' || c == ')')
+          .to_string()
+      })
+      .filter(|scope| !scope.is_empty());
+
+    self.prefix = self
+      .prefix
+      .map(|prefix| prefix.trim().to_string())
+      .filter(|prefix| !prefix.is_empty());
+
+    // Models sometimes repeat the type in the subject or add a period
+    let subject = self.subject.lines().next().unwrap_or_default().trim();
+    let repeats_type = |head: &str| {
+      let head = head.trim_end_matches('!');
+      head == self.r#type || head.starts_with(&format!("{}(", self.r#type))
+    };
+    let subject = subject
+      .split_once(':')
+      .filter(|(head, _)| repeats_type(head))
+      .map(|(_, subject)| subject.trim_start())
+      .unwrap_or(subject);
+    self.subject = subject.trim_end_matches('.').trim().to_string();
+    if self.subject.is_empty() {
+      return Err(MessageError::EmptySubject);
+    }
+
+    self.body = trim_lines(self.body);
+    self.footers = trim_lines(self.footers);
+    if !self.breaking {
+      self.breaking = self.footers.iter().any(|footer| {
+        footer.starts_with("BREAKING CHANGE:") || footer.starts_with("BREAKING-CHANGE:")
+      });
+    }
+
+    Ok(self)
+  }
+
+  /// Assemble `prefix type(scope)!: subject`, the body and the footers
+  pub fn render(&self) -> String {
+    let mut message = String::new();
+    if let Some(prefix) = &self.prefix {
+      message.push_str(prefix);
+      message.push(' ');
+    }
+    message.push_str(&self.r#type);
+    if let Some(scope) = &self.scope {
+      message.push_str(&format!("({scope})"));
+    }
+    if self.breaking {
+      message.push('!');
+    }
+    message.push_str(&format!(": {}", self.subject));
+
+    for section in [&self.body, &self.footers] {
+      if !section.is_empty() {
+        message.push_str("\n\n");
+        message.push_str(&section.join("\n"));
+      }
+    }
+
+    message
+  }
+}
+
+fn trim_lines(lines: Vec<String>) -> Vec<String> {
+  lines
+    .iter()
+    .flat_map(|line| line.lines())
+    .map(|line| line.trim_end().to_string())
+    .filter(|line| !line.trim().is_empty())
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn responses_are_repaired_into_a_conventional_subject() {
+    let cases = [
+      // (response, rendered message)
+      (
+        r#"{"type": "feat", "scope": "api", "subject": "add retries"}"#,
+        "feat(api): add retries",
+      ),
+      (
+        "```json\n{\"type\": \"fix\", \"scope\": null, \"subject\": \"handle EOF\"}\n```",
+        "fix: handle EOF",
+      ),
+      (
+        r#"{"type": "fix", "scope": "git", "subject": "fix(git)!: quote paths"}"#,
+        "fix(git): quote paths",
+      ),
+      (
+        r#"{"type": "docs", "scope": " (readme) ", "subject": "fix typo."}"#,
+        "docs(readme): fix typo",
+      ),
+      (
+        r#"{"type": "Feature", "scope": "", "subject": "add flag"}"#,
+        "chore: add flag",
+      ),
+      (
+        r#"{"type": "feat", "scope": "config", "subject": "rename keys",
+            "footers": ["BREAKING CHANGE: keys are renamed"]}"#,
+        "feat(config)!: rename keys\n\nBREAKING CHANGE: keys are renamed",
+      ),
+      (
+        r#"{"type": "fix", "scope": null, "subject": "quote paths", "prefix": " [ACME] "}"#,
+        "[ACME] fix: quote paths",
+      ),
+    ];
+
+    for (response, expected) in cases {
+      let message = CommitMessage::parse(response).unwrap();
+      assert_eq!(message.render(), expected, "{response}");
+    }
+  }
+
+  #[test]
+  fn invalid_responses_are_errors() {
+    for response in [
+      r#"{"type": "fix", "scope": null, "subject": " . "}"#,
+      r#"{"type": "fix", "scope": null, "subject": "fix: "}"#,
+    ] {
+      assert!(
+        matches!(
+          CommitMessage::parse(response),
+          Err(MessageError::EmptySubject)
+        ),
+        "{response}"
+      );
+    }
+
+    assert!(matches!(
+      CommitMessage::parse("no commit message here"),
+      Err(MessageError::InvalidJson(_))
+    ));
+  }
+
+  #[test]
+  fn render_separates_the_body_and_footers() {
+    let message = CommitMessage::parse(
+      r#"{"type": "feat", "scope": "cli", "breaking": true, "subject": "drop --fast",
+          "body": ["- remove the flag  ", "", "- update docs\n- add test"],
+          "footers": ["Closes #12", "  "]}"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+      message.render(),
+      "feat(cli)!: drop --fast\n\n- remove the flag\n- update docs\n- add test\n\nCloses #12"
+    );
+  }
+}
@@ -0,0 +1,157 @@
+use crate::client::{
+  AIClient, ClientError, GenerateResponseResult, ReasoningEffort, ResponseFuture, Result, UsageInfo,
+};
+use crate::message::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::io::AsyncWriteExt;
+
+/// Scripted responses, read from the fixture file named by `mock:<path>`
+#[derive(Debug, Deserialize)]
+struct Fixture {
+  /// Returned in order, one per request; the last one is repeated once they run out
+  responses: Vec<FixtureResponse>,
+  /// File the requests are appended to as JSON lines, so tests can assert on the prompts
+  #[serde(default)]
+  record: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FixtureResponse {
+  message: String,
+  #[serde(default)]
+  usage: FixtureUsage,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct FixtureUsage {
+  input_tokens: u32,
+  output_tokens: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct RecordedRequest {
+  max_tokens: u32,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  reasoning_effort: Option<String>,
+  system: String,
+  user: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  schema: Option<Value>,
+}
+
+/// Deterministic provider for tests and demos, it never leaves the machine
+pub struct MockClient {
+  fixture: String,
+  /// Number of requests answered so far
+  requests: AtomicUsize,
+  max_tokens: u32,
+  reasoning_effort: Option<ReasoningEffort>,
+}
+
+impl MockClient {
+  pub fn new(fixture: &str) -> Self {
+    Self {
+      fixture: fixture.to_string(),
+      requests: AtomicUsize::new(0),
+      max_tokens: 500,
+      reasoning_effort: None,
+    }
+  }
+}
+
+impl AIClient for MockClient {
+  fn set_model(&mut self, model: String) {
+    self.fixture = model;
+  }
+
+  fn set_max_tokens(&mut self, max_tokens: u32) {
+    self.max_tokens = max_tokens;
+  }
+
+  fn set_temperature(&mut self, _temperature: f32) {}
+
+  fn set_reasoning_effort(&mut self, effort: ReasoningEffort) -> bool {
+    self.reasoning_effort = Some(effort);
+    true
+  }
+
+  fn generate_response(&self, system_prompt: String, user_prompt: String) -> ResponseFuture<'_> {
+    Box::pin(self.generate_response_impl(system_prompt, user_prompt, None))
+  }
+
+  fn generate_structured_response(
+    &self,
+    system_prompt: String,
+    user_prompt: String,
+    schema: JsonSchema,
+  ) -> ResponseFuture<'_> {
+    Box::pin(self.generate_response_impl(system_prompt, user_prompt, Some(schema)))
+  }
+}
+
+impl MockClient {
+  async fn generate_response_impl(
+    &self,
+    system_prompt: String,
+    user_prompt: String,
+    schema: Option<JsonSchema>,
+  ) -> Result<GenerateResponseResult> {
+    let content = tokio::fs::read_to_string(&self.fixture)
+      .await
+      .map_err(|_| {
+        ClientError::RequestFailed(format!("Failed to read fixture {}", self.fixture))
+      })?;
+    let fixture: Fixture = serde_json::from_str(&content).map_err(|e| {
+      ClientError::RequestFailed(format!("Failed to parse fixture {}: {e}", self.fixture))
+    })?;
+
+    if let Some(record) = &fixture.record {
+      let request = RecordedRequest {
+        max_tokens: self.max_tokens,
+        reasoning_effort: self
+          .reasoning_effort
+          .map(|effort| effort.as_str().to_string()),
+        system: system_prompt,
+        user: user_prompt,
+        schema: schema.map(|schema| schema.schema),
+      };
+      let line = serde_json::to_string(&request)
+        .map_err(|e| ClientError::FailedToSend(e.to_string()))?
+        + "\n";
+      let failed = |e: std::io::Error| ClientError::FailedToSend(format!("{record}: {e}"));
+
+      let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(record)
+        .await
+        .map_err(failed)?;
+      file.write_all(line.as_bytes()).await.map_err(failed)?;
+    }
+
+    let index = self.requests.fetch_add(1, Ordering::SeqCst);
+    let response = fixture
+      .responses
+      .get(index)
+      .or(fixture.responses.last())
+      .ok_or_else(|| {
+        ClientError::RequestFailed(format!("Fixture {} has no responses", self.fixture))
+      })?;
+
+    let usage = &response.usage;
+    Ok(GenerateResponseResult {
+      message: response.message.trim().to_string(),
+      usage: UsageInfo {
+        input_tokens: usage.input_tokens,
+        output_tokens: usage.output_tokens,
+        total_tokens: usage.input_tokens + usage.output_tokens,
+        cache_creation_input_tokens: 0,
+        cache_read_input_tokens: 0,
+        reasoning_tokens: 0,
+      },
+    })
+  }
+}
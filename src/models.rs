@@ -0,0 +1,476 @@
+use crate::client::UsageInfo;
+use crate::config::ModelConfig;
+use std::collections::HashMap;
+
+/// Rough number of characters per token of English text and code, used to estimate prompt sizes
+pub const CHARS_PER_TOKEN: usize = 4;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ModelError {
+  #[error(
+    "{model} generates at most {limit} output tokens, but {requested} were requested; lower --max-tokens or --reasoning-effort"
+  )]
+  MaxTokensExceeded {
+    model: String,
+    requested: u32,
+    limit: u32,
+  },
+}
+
+/// Prices in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pricing {
+  pub input: f64,
+  pub output: f64,
+  /// Input tokens read from the prompt cache
+  pub cached_input: f64,
+}
+
+/// Request parameters a model accepts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capabilities {
+  pub temperature: bool,
+  pub streaming: bool,
+  pub structured_output: bool,
+  pub caching: bool,
+  /// Accepts `--reasoning-effort`
+  pub reasoning: bool,
+}
+
+const CHAT: Capabilities = Capabilities {
+  temperature: true,
+  streaming: true,
+  structured_output: true,
+  caching: true,
+  reasoning: false,
+};
+
+/// Chat models that can also think before answering, like Claude 4 and Gemini 2.5
+const THINKING: Capabilities = Capabilities {
+  reasoning: true,
+  ..CHAT
+};
+
+/// OpenAI reasoning models, which reject a temperature
+const REASONING: Capabilities = Capabilities {
+  temperature: false,
+  reasoning: true,
+  ..CHAT
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelInfo {
+  pub provider: String,
+  /// Model name, also matching dated snapshots like `claude-sonnet-4-20250514`
+  pub name: String,
+  /// Input and output tokens of a request, 0 when unknown
+  pub context_window: u32,
+  /// 0 when unknown
+  pub max_output_tokens: u32,
+  pub capabilities: Capabilities,
+  pub pricing: Option<Pricing>,
+}
+
+impl ModelInfo {
+  /// `provider:name`, as passed to `--model`
+  pub fn id(&self) -> String {
+    format!("{}:{}", self.provider, self.name)
+  }
+
+  /// Check the requested output tokens against the limit of the model
+  pub fn validate(&self, max_tokens: u32, reasoning_budget: u32) -> Result<(), ModelError> {
+    let requested = max_tokens.saturating_add(reasoning_budget);
+    if self.max_output_tokens > 0 && requested > self.max_output_tokens {
+      return Err(ModelError::MaxTokensExceeded {
+        model: self.id(),
+        requested,
+        limit: self.max_output_tokens,
+      });
+    }
+
+    Ok(())
+  }
+
+  /// Input tokens left once `output_tokens` are reserved for the response, `None` when the
+  /// context window is unknown
+  pub fn input_budget(&self, output_tokens: u32) -> Option<u32> {
+    (self.context_window > 0).then(|| self.context_window.saturating_sub(output_tokens))
+  }
+
+  /// Estimated price of the tokens in USD
+  pub fn cost(&self, usage: &UsageInfo) -> Option<f64> {
+    let pricing = self.pricing?;
+    // Cache writes are billed at least at the input rate, so they are estimated at it
+    let input = (usage.input_tokens + usage.cache_creation_input_tokens) as f64 * pricing.input;
+    let cached = usage.cache_read_input_tokens as f64 * pricing.cached_input;
+    let output = usage.output_tokens as f64 * pricing.output;
+
+    Some((input + cached + output) / 1_000_000.0)
+  }
+
+  fn apply(&mut self, config: &ModelConfig) {
+    let capabilities = &mut self.capabilities;
+    if let Some(context_window) = config.context_window {
+      self.context_window = context_window;
+    }
+    if let Some(max_output_tokens) = config.max_output_tokens {
+      self.max_output_tokens = max_output_tokens;
+    }
+    if let Some(temperature) = config.temperature {
+      capabilities.temperature = temperature;
+    }
+    if let Some(streaming) = config.streaming {
+      capabilities.streaming = streaming;
+    }
+    if let Some(structured_output) = config.structured_output {
+      capabilities.structured_output = structured_output;
+    }
+    if let Some(caching) = config.caching {
+      capabilities.caching = caching;
+    }
+    if let Some(reasoning) = config.reasoning {
+      capabilities.reasoning = reasoning;
+    }
+
+    if config.input_price.is_some()
+      || config.output_price.is_some()
+      || config.cached_input_price.is_some()
+    {
+      let base = self.pricing;
+      let input = config.input_price.or(base.map(|p| p.input)).unwrap_or(0.0);
+      self.pricing = Some(Pricing {
+        input,
+        output: config
+          .output_price
+          .or(base.map(|p| p.output))
+          .unwrap_or(0.0),
+        // Without a cache discount, cached tokens cost as much as any other input
+        cached_input: config
+          .cached_input_price
+          .or(base.map(|p| p.cached_input))
+          .unwrap_or(input),
+      });
+    }
+  }
+}
+
+/// `(provider, name, context window, max output tokens, capabilities, input, cached input, output)`
+type BuiltinModel = (
+  &'static str,
+  &'static str,
+  u32,
+  u32,
+  Capabilities,
+  f64,
+  f64,
+  f64,
+);
+
+const BUILTIN_MODELS: &[BuiltinModel] = &[
+  (
+    "anthropic",
+    "claude-opus-4-1",
+    200_000,
+    32_000,
+    THINKING,
+    15.0,
+    1.5,
+    75.0,
+  ),
+  (
+    "anthropic",
+    "claude-opus-4",
+    200_000,
+    32_000,
+    THINKING,
+    15.0,
+    1.5,
+    75.0,
+  ),
+  (
+    "anthropic",
+    "claude-sonnet-4-5",
+    200_000,
+    64_000,
+    THINKING,
+    3.0,
+    0.3,
+    15.0,
+  ),
+  (
+    "anthropic",
+    "claude-sonnet-4",
+    200_000,
+    64_000,
+    THINKING,
+    3.0,
+    0.3,
+    15.0,
+  ),
+  (
+    "anthropic",
+    "claude-haiku-4-5",
+    200_000,
+    64_000,
+    THINKING,
+    1.0,
+    0.1,
+    5.0,
+  ),
+  (
+    "anthropic",
+    "claude-3-7-sonnet",
+    200_000,
+    64_000,
+    THINKING,
+    3.0,
+    0.3,
+    15.0,
+  ),
+  (
+    "anthropic",
+    "claude-3-5-haiku",
+    200_000,
+    8_192,
+    CHAT,
+    0.8,
+    0.08,
+    4.0,
+  ),
+  (
+    "openai", "gpt-5", 400_000, 128_000, REASONING, 1.25, 0.125, 10.0,
+  ),
+  (
+    "openai",
+    "gpt-5-mini",
+    400_000,
+    128_000,
+    REASONING,
+    0.25,
+    0.025,
+    2.0,
+  ),
+  (
+    "openai",
+    "gpt-5-nano",
+    400_000,
+    128_000,
+    REASONING,
+    0.05,
+    0.005,
+    0.4,
+  ),
+  (
+    "openai",
+    "gpt-5-chat",
+    128_000,
+    16_384,
+    CHAT,
+    1.25,
+    0.125,
+    10.0,
+  ),
+  ("openai", "gpt-4.1", 1_047_576, 32_768, CHAT, 2.0, 0.5, 8.0),
+  (
+    "openai",
+    "gpt-4.1-mini",
+    1_047_576,
+    32_768,
+    CHAT,
+    0.4,
+    0.1,
+    1.6,
+  ),
+  (
+    "openai",
+    "gpt-4.1-nano",
+    1_047_576,
+    32_768,
+    CHAT,
+    0.1,
+    0.025,
+    0.4,
+  ),
+  ("openai", "gpt-4o", 128_000, 16_384, CHAT, 2.5, 1.25, 10.0),
+  (
+    "openai",
+    "gpt-4o-mini",
+    128_000,
+    16_384,
+    CHAT,
+    0.15,
+    0.075,
+    0.6,
+  ),
+  ("openai", "o3", 200_000, 100_000, REASONING, 2.0, 0.5, 8.0),
+  (
+    "openai", "o3-mini", 200_000, 100_000, REASONING, 1.1, 0.55, 4.4,
+  ),
+  (
+    "openai", "o4-mini", 200_000, 100_000, REASONING, 1.1, 0.275, 4.4,
+  ),
+  (
+    "google",
+    "gemini-2.5-pro",
+    1_048_576,
+    65_536,
+    THINKING,
+    1.25,
+    0.31,
+    10.0,
+  ),
+  (
+    "google",
+    "gemini-2.5-flash",
+    1_048_576,
+    65_536,
+    THINKING,
+    0.3,
+    0.075,
+    2.5,
+  ),
+  (
+    "google",
+    "gemini-2.5-flash-lite",
+    1_048_576,
+    65_536,
+    THINKING,
+    0.1,
+    0.025,
+    0.4,
+  ),
+  (
+    "google",
+    "gemini-2.0-flash",
+    1_048_576,
+    8_192,
+    CHAT,
+    0.1,
+    0.025,
+    0.4,
+  ),
+];
+
+/// Known models, the built-in catalog extended by the `[models]` table of the config
+#[derive(Debug, Clone)]
+pub struct ModelRegistry {
+  models: Vec<ModelInfo>,
+}
+
+impl Default for ModelRegistry {
+  fn default() -> Self {
+    let models = BUILTIN_MODELS
+      .iter()
+      .map(
+        |&(
+          provider,
+          name,
+          context_window,
+          max_output_tokens,
+          capabilities,
+          input,
+          cached,
+          output,
+        )| {
+          ModelInfo {
+            provider: provider.to_string(),
+            name: name.to_string(),
+            context_window,
+            max_output_tokens,
+            capabilities,
+            pricing: Some(Pricing {
+              input,
+              output,
+              cached_input: cached,
+            }),
+          }
+        },
+      )
+      .collect();
+
+    Self { models }
+  }
+}
+
+impl ModelRegistry {
+  /// The built-in catalog with the config entries applied. An entry for a known model overrides
+  /// its settings, others add a model, starting from the model they are a snapshot of if any.
+  pub fn new(config: &HashMap<String, ModelConfig>) -> Self {
+    let mut registry = Self::default();
+
+    let mut ids: Vec<&String> = config.keys().collect();
+    ids.sort();
+    for id in ids {
+      let Some((provider, name)) = id.split_once(':') else {
+        println!("[OUTPUT] Warning: ignoring models.\"{id}\", expected provider:model");
+        continue;
+      };
+
+      let index = match registry.position(provider, name) {
+        Some(index) if registry.models[index].name == name => index,
+        base => {
+          let mut model = base
+            .map(|index| registry.models[index].clone())
+            .unwrap_or_else(|| ModelInfo {
+              provider: provider.to_string(),
+              name: String::new(),
+              context_window: 0,
+              max_output_tokens: 0,
+              capabilities: Capabilities {
+                reasoning: true,
+                ..CHAT
+              },
+              pricing: None,
+            });
+          model.name = name.to_string();
+          registry.models.push(model);
+          registry.models.len() - 1
+        }
+      };
+      registry.models[index].apply(&config[id]);
+    }
+
+    registry
+  }
+
+  /// The entry of `model` (`provider:name`), matching dated snapshots and `-latest` aliases by
+  /// the longest known name they start with
+  pub fn find(&self, model: &str) -> Option<&ModelInfo> {
+    let (provider, name) = model.split_once(':')?;
+    self
+      .position(provider, name)
+      .map(|index| &self.models[index])
+  }
+
+  fn position(&self, provider: &str, name: &str) -> Option<usize> {
+    self
+      .models
+      .iter()
+      .enumerate()
+      .filter(|(_, model)| model.provider == provider)
+      .filter(|(_, model)| {
+        name == model.name
+          || name
+            .strip_prefix(model.name.as_str())
+            .is_some_and(|rest| rest.starts_with('-'))
+      })
+      .max_by_key(|(_, model)| model.name.len())
+      .map(|(index, _)| index)
+  }
+
+  /// Models of `provider`, or of every provider, sorted by provider
+  pub fn models(&self, provider: Option<&str>) -> Vec<&ModelInfo> {
+    let mut models: Vec<&ModelInfo> = self
+      .models
+      .iter()
+      .filter(|model| provider.is_none_or(|provider| model.provider == provider))
+      .collect();
+    models.sort_by(|a, b| a.provider.cmp(&b.provider));
+    models
+  }
+}
+
+/// Estimated token count of `text`
+pub fn estimate_tokens(text: &str) -> u32 {
+  text.len().div_ceil(CHARS_PER_TOKEN) as u32
+}
@@ -1,6 +1,11 @@
-use crate::client::{AIClient, ClientError, GenerateResponseResult, Result, UsageInfo};
+use crate::client::{
+  AIClient, ClientError, GenerateResponseResult, ReasoningEffort, ResponseFuture, Result, UsageInfo,
+};
+use crate::http;
+use crate::message::JsonSchema;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Debug, Serialize)]
 struct OpenAIMessage {
@@ -12,52 +17,187 @@ struct OpenAIMessage {
 struct OpenAIRequest {
   model: String,
   input: Vec<OpenAIMessage>,
-  max_tokens: u32,
-  temperature: f32,
+  /// Includes the reasoning tokens of reasoning models
+  max_output_tokens: u32,
+  /// Rejected by reasoning models
+  #[serde(skip_serializing_if = "Option::is_none")]
+  temperature: Option<f32>,
   stream: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  reasoning: Option<ReasoningOptions>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  text: Option<TextOptions>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReasoningOptions {
+  effort: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TextOptions {
+  format: TextFormat,
+}
+
+#[derive(Debug, Serialize)]
+struct TextFormat {
+  r#type: String,
+  name: String,
+  description: String,
+  schema: Value,
+  strict: bool,
 }
 
 #[derive(Debug, Deserialize)]
 struct OpenAIResponse {
-  output: Vec<ContentBlock>,
+  #[serde(default)]
+  status: Option<String>,
+  #[serde(default)]
+  incomplete_details: Option<IncompleteDetails>,
+  output: Vec<OutputItem>,
   usage: OpenAIUsage,
 }
 
+#[derive(Debug, Deserialize)]
+struct IncompleteDetails {
+  reason: String,
+}
+
 #[derive(Debug, Deserialize)]
 struct OpenAIUsage {
   input_tokens: u32,
   output_tokens: u32,
   total_tokens: u32,
+  #[serde(default)]
+  input_tokens_details: InputTokensDetails,
+  #[serde(default)]
+  output_tokens_details: OutputTokensDetails,
+}
+
+/// OpenAI caches long prompt prefixes automatically, cached tokens are part of `input_tokens`
+#[derive(Debug, Default, Deserialize)]
+struct InputTokensDetails {
+  #[serde(default)]
+  cached_tokens: u32,
+}
+
+/// Reasoning tokens are part of `output_tokens`
+#[derive(Debug, Default, Deserialize)]
+struct OutputTokensDetails {
+  #[serde(default)]
+  reasoning_tokens: u32,
 }
 
+/// Reasoning models output `reasoning` items before the `message` holding the answer
 #[derive(Debug, Deserialize)]
-struct ContentBlock {
-  content: Vec<OutputText>,
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OutputItem {
+  Message {
+    #[serde(default)]
+    content: Vec<OutputContent>,
+  },
+  #[serde(other)]
+  Other,
 }
 
 #[derive(Debug, Deserialize)]
-struct OutputText {
-  text: String,
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OutputContent {
+  OutputText {
+    text: String,
+  },
+  Refusal {
+    refusal: String,
+  },
+  #[serde(other)]
+  Other,
 }
 
+/// Default API version for Azure OpenAI, the first one supporting the Responses API
+pub const AZURE_DEFAULT_API_VERSION: &str = "2025-04-01-preview";
+
+/// API root including the version, overridable with `OPENAI_BASE_URL`
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+
 pub struct OpenAIClient {
   client: Client,
   api_key: String,
+  /// Responses endpoint, including the `api-version` query for Azure
+  url: String,
+  /// Azure expects the key in an `api-key` header instead of a bearer token
+  azure: bool,
   model: String,
+  /// Whether the model reasons as known from the model catalog, `None` to tell by its name
+  reasoning_model: Option<bool>,
   max_tokens: u32,
   temperature: f32,
+  reasoning_effort: Option<ReasoningEffort>,
+}
+
+/// o-series and GPT-5 models reason before answering and reject `temperature`
+fn is_reasoning_model(model: &str) -> bool {
+  let mut chars = model.chars();
+  let o_series = chars.next() == Some('o') && chars.next().is_some_and(|c| c.is_ascii_digit());
+  let gpt_5 = model.starts_with("gpt-5") && !model.starts_with("gpt-5-chat");
+  o_series || gpt_5
 }
 
 impl OpenAIClient {
-  pub fn new(api_key: String) -> Self {
+  pub fn new(api_key: String, client: Client) -> Self {
     Self {
-      client: Client::new(),
+      client,
       api_key,
+      url: format!("{OPENAI_BASE_URL}/responses"),
+      azure: false,
       model: "gpt-4.1".to_string(),
+      reasoning_model: None,
       max_tokens: 500,
       temperature: 0.7,
+      reasoning_effort: None,
     }
   }
+
+  /// Send requests to another API root, e.g. a proxy or a local stand-in
+  pub fn with_base_url(self, base_url: &str) -> Self {
+    Self {
+      url: format!("{}/responses", base_url.trim_end_matches('/')),
+      ..self
+    }
+  }
+
+  /// Azure OpenAI client for a resource name like `my-resource` or a full endpoint URL like
+  /// `https://my-resource.openai.azure.com`. The deployment name is passed as the model: the
+  /// Responses API is served at `/openai/responses` only, the `/openai/deployments/<deployment>`
+  /// routes are the older Chat Completions API, whose responses this client doesn't parse.
+  pub fn azure(api_key: String, client: Client, resource: &str, api_version: &str) -> Self {
+    let endpoint = if resource.contains("://") {
+      resource.trim_end_matches('/').to_string()
+    } else {
+      format!("https://{resource}.openai.azure.com")
+    };
+
+    Self {
+      url: format!("{endpoint}/openai/responses?api-version={api_version}"),
+      azure: true,
+      ..Self::new(api_key, client)
+    }
+  }
+
+  /// Whether the model reasons, from the model catalog when it is listed there. Otherwise the
+  /// model name decides, so Azure deployments are only recognized when named like the model,
+  /// e.g. `o4-mini`, or when listed under `[models]`.
+  pub fn with_reasoning_model(self, reasoning_model: Option<bool>) -> Self {
+    Self {
+      reasoning_model,
+      ..self
+    }
+  }
+
+  fn is_reasoning(&self) -> bool {
+    self
+      .reasoning_model
+      .unwrap_or_else(|| is_reasoning_model(&self.model))
+  }
 }
 
 impl AIClient for OpenAIClient {
@@ -73,22 +213,36 @@ impl AIClient for OpenAIClient {
     self.temperature = temperature;
   }
 
-  fn generate_response(
+  fn set_reasoning_effort(&mut self, effort: ReasoningEffort) -> bool {
+    if !self.is_reasoning() {
+      return false;
+    }
+
+    self.reasoning_effort = Some(effort);
+    true
+  }
+
+  fn generate_response(&self, system_prompt: String, user_prompt: String) -> ResponseFuture<'_> {
+    Box::pin(self.generate_response_impl(system_prompt, user_prompt, None))
+  }
+
+  fn generate_structured_response(
     &self,
     system_prompt: String,
     user_prompt: String,
-  ) -> std::pin::Pin<
-    Box<dyn std::future::Future<Output = Result<GenerateResponseResult>> + Send + '_>,
-  > {
-    Box::pin(self.generate_response_impl(system_prompt, user_prompt))
+    schema: JsonSchema,
+  ) -> ResponseFuture<'_> {
+    Box::pin(self.generate_response_impl(system_prompt, user_prompt, Some(schema)))
   }
 }
 
 impl OpenAIClient {
+  /// With a schema, the output text is constrained to JSON matching it via `text.format`
   async fn generate_response_impl(
     &self,
     system_prompt: String,
     user_prompt: String,
+    schema: Option<JsonSchema>,
   ) -> Result<GenerateResponseResult> {
     let system_message = OpenAIMessage {
       role: "system".to_string(),
@@ -100,23 +254,50 @@ impl OpenAIClient {
       content: user_prompt,
     };
 
+    // Reasoning tokens count against `max_output_tokens`, reserve them on top of the answer
+    let reasoning = self.reasoning_effort.is_some() || self.is_reasoning();
+    let max_output_tokens = if reasoning {
+      let effort = self.reasoning_effort.unwrap_or(ReasoningEffort::Medium);
+      self.max_tokens + effort.budget_tokens()
+    } else {
+      self.max_tokens
+    };
+
     let request = OpenAIRequest {
       model: self.model.clone(),
       input: vec![system_message, user_message],
-      max_tokens: self.max_tokens,
-      temperature: self.temperature,
+      max_output_tokens,
+      temperature: (!reasoning).then_some(self.temperature),
       stream: false,
+      reasoning: self.reasoning_effort.map(|effort| ReasoningOptions {
+        effort: effort.as_str().to_string(),
+      }),
+      text: schema.map(|schema| TextOptions {
+        format: TextFormat {
+          r#type: "json_schema".to_string(),
+          name: schema.name,
+          description: schema.description,
+          schema: schema.schema,
+          strict: true,
+        },
+      }),
     };
 
-    let response = self
+    let request_builder = self
       .client
-      .post("https://api.openai.com/v1/responses")
-      .header("Content-Type", "application/json")
-      .header("Authorization", format!("Bearer {}", &self.api_key))
+      .post(&self.url)
+      .header("Content-Type", "application/json");
+    let request_builder = if self.azure {
+      request_builder.header("api-key", &self.api_key)
+    } else {
+      request_builder.header("Authorization", format!("Bearer {}", &self.api_key))
+    };
+
+    let response = request_builder
       .json(&request)
       .send()
       .await
-      .map_err(|_| ClientError::FailedToSend)?;
+      .map_err(http::send_error)?;
 
     if !response.status().is_success() {
       let error_text = response.text().await.unwrap_or_default();
@@ -128,19 +309,86 @@ impl OpenAIClient {
       .await
       .map_err(|_| ClientError::FailedToParseResponse)?;
 
-    let message = api_response
-      .output
-      .first()
-      .and_then(|block| block.content.first())
-      .map(|block| block.text.trim().to_string())
-      .unwrap_or_default();
+    let mut message = String::new();
+    for item in &api_response.output {
+      let OutputItem::Message { content } = item else {
+        continue;
+      };
+      for content in content {
+        match content {
+          OutputContent::OutputText { text } => message.push_str(text),
+          OutputContent::Refusal { refusal } => {
+            return Err(ClientError::RequestFailed(format!(
+              "The model refused: {refusal}"
+            )));
+          }
+          OutputContent::Other => {}
+        }
+      }
+    }
+    let message = message.trim().to_string();
 
+    if message.is_empty()
+      && api_response.status.as_deref() == Some("incomplete")
+      && let Some(details) = &api_response.incomplete_details
+    {
+      return Err(ClientError::RequestFailed(format!(
+        "The response is incomplete ({}), increase --max-tokens or lower --reasoning-effort",
+        details.reason
+      )));
+    }
+
+    let usage = api_response.usage;
+    let cached_tokens = usage.input_tokens_details.cached_tokens;
     let usage = UsageInfo {
-      input_tokens: api_response.usage.input_tokens,
-      output_tokens: api_response.usage.output_tokens,
-      total_tokens: api_response.usage.total_tokens,
+      input_tokens: usage.input_tokens.saturating_sub(cached_tokens),
+      output_tokens: usage.output_tokens,
+      total_tokens: usage.total_tokens,
+      cache_creation_input_tokens: 0,
+      cache_read_input_tokens: cached_tokens,
+      reasoning_tokens: usage.output_tokens_details.reasoning_tokens,
     };
 
     Ok(GenerateResponseResult { message, usage })
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn azure_urls_come_from_the_resource_or_the_endpoint() {
+    let client = OpenAIClient::azure(String::new(), Client::new(), "my-resource", "2025-04-01");
+    assert_eq!(
+      client.url,
+      "https://my-resource.openai.azure.com/openai/responses?api-version=2025-04-01"
+    );
+
+    let client = OpenAIClient::azure(
+      String::new(),
+      Client::new(),
+      "https://gateway.example.com/",
+      "2025-04-01",
+    );
+    assert_eq!(
+      client.url,
+      "https://gateway.example.com/openai/responses?api-version=2025-04-01"
+    );
+  }
+
+  #[test]
+  fn the_catalog_decides_whether_a_deployment_reasons() {
+    let mut client = OpenAIClient::azure(String::new(), Client::new(), "r", "v");
+    client.set_model("prod-gpt-4o".to_string());
+    assert!(!client.set_reasoning_effort(ReasoningEffort::Low));
+    client.set_model("o4-mini".to_string());
+    assert!(client.set_reasoning_effort(ReasoningEffort::Low));
+
+    let mut client = client.with_reasoning_model(Some(false));
+    assert!(!client.set_reasoning_effort(ReasoningEffort::Low));
+    client.set_model("prod-gpt-4o".to_string());
+    let mut client = client.with_reasoning_model(Some(true));
+    assert!(client.set_reasoning_effort(ReasoningEffort::Low));
+  }
+}
@@ -1,26 +1,10 @@
-use crate::analysis::{format_recent_commits, group_files_by_type};
+use crate::analysis::format_recent_commits;
 use crate::client::Result;
+use crate::diff::Diff;
 
-pub fn get_commit_system_prompt() -> String {
-  "You are an expert at generating git commit messages following conventional commit standards. Your response should only contain the commit message, nothing else.".to_string()
-}
-
-pub async fn get_commit_user_prompt(
-  branch_name: String,
-  scopes: String,
-  is_nx_repo: bool,
-  diff: String,
-  modified_files: Vec<String>,
-  recent_commits: Vec<String>,
-  change_analysis: String,
-) -> Result<String> {
-  // Group files by type
-  let grouped_files = group_files_by_type(modified_files.clone());
-
-  // Format recent commits
-  let recent_commits_str = format_recent_commits(recent_commits);
-
-  Ok(format!("# Git Commit Message Generation Prompt
+/// Instructions shared by every commit message request. They never depend on the staged
+/// changes, so providers can cache them as a prompt prefix.
+const COMMIT_INSTRUCTIONS: &str = "# Git Commit Message Generation Prompt
 
 You are an expert at writing clear, concise, and meaningful git commit messages following conventional commit patterns.
 
@@ -53,12 +37,15 @@ Follow the pattern: `<type>[optional scope]: <description>`
 ### Scope Guidelines
 
 - If a comma-separated list of scopes is provided in the data, use ONLY scopes from this list
-- For Nx repositories (when is_nx_repo is true), the scopes list will contain app and lib directory names
+- Scopes may have a description in parentheses, the description is not part of the scope name
+- If matching scopes are provided, they were matched against the modified file paths; choose the scope from them
+- The affected packages list contains the workspace packages (Nx, Cargo, pnpm/npm, Go) owning the changed files
 - Only use a scope if it clearly matches the changes being made
 - If no scope from the provided list is suitable, omit the scope entirely
 - Do not invent scopes that aren't in the provided list
-- For Nx repositories, prefer using the app or lib name that contains the changed files as the scope
-- If the scopes list is empty and it's not an Nx repo, you may derive a scope from the directory names in the diff
+- If affected packages are listed, prefer one of them as the scope; if several are affected, use the one most central to the change
+- Code owners list the teams owning the changed files; when no other scope applies, a team's area (e.g. `@acme/payments` -> `payments`) can hint at the scope
+- If the scopes list and the affected packages are empty, you may derive a scope from the directory names in the diff
 - From the commit history, prepend any prefixs that is commonly used in the previous commits
 
 Examples with prefix:
@@ -75,6 +62,15 @@ Examples without scope:
 - `fix: resolve cross-platform compatibility issue`
 - `refactor: simplify error handling logic`
 
+### Symbol Changes
+- Symbol changes are extracted from the syntax trees of the old and new file contents, they are accurate and complete for the listed files
+- Prefer them over guesses from the diff when deciding between feat, fix and refactor, and when naming what changed
+
+### Dependency Changes
+- Dependency changes are read from the manifests and lockfiles, including lockfiles left out of the diff, and their versions are exact
+- If the commit only changes dependencies, use the suggested commit listed with them: `build(deps):` for runtime dependencies, `chore(deps):` when only development dependencies change
+- Mention dependency versions exactly as listed, never guess them from the diff
+
 ### Multi-file Changes
 - Focus on the primary purpose of the change
 - Use the most appropriate type for the overall change
@@ -103,6 +99,8 @@ Resolves #789
 ```
 
 ### Breaking Changes
+- Breaking changes are detected by comparing the public API (`pub` Rust items, `export`ed TypeScript declarations) before and after the change
+- If breaking changes are listed in the data, set breaking to true and use the footer draft as the `BREAKING CHANGE:` footer, rewording it for clarity if needed
 - Always include `BREAKING CHANGE:` in footer when applicable
 - Describe what changed and migration path if needed
 - Use when changes break backward compatibility
@@ -112,32 +110,132 @@ Resolves #789
 - Use `Fixes #456` for bug fixes that resolve issues
 - Use `Resolves #789` for general issue resolution
 - Multiple references are allowed
+- Do not add `Signed-off-by`, `Co-authored-by` or other git trailers, they are added automatically
+- If issue keys are listed in the data, they were extracted from the branch name and are added to the message automatically; do not add references to them yourself
 
 Generate commit messages that clearly communicate the intent and impact of the staged changes.
 
-Analyze the branch name, diff and scopes attached, to generate a conventional commit message.
+## Response Format
+
+Return the commit message as its parts, they are assembled into `<prefix> <type>(<scope>)!: <subject>`, the body and the footers:
+- **type**: one of the types above
+- **scope**: the scope without parentheses, or null when no scope applies
+- **breaking**: true when the change breaks backward compatibility, this adds the `!` marker
+- **subject**: the description after the colon, without type, scope or trailing period
+- **body**: the body lines, e.g. bullet points starting with `- `, or an empty list
+- **footers**: footer lines like `BREAKING CHANGE: ...` or `Closes #123`, or an empty list
+- **prefix**: the prefix commonly used by previous commits, e.g. `[Company]`, or null
+";
+
+pub fn get_commit_system_prompt() -> String {
+  format!(
+    "You are an expert at generating git commit messages following conventional commit standards. Your response contains only the parts of the commit message, nothing else.\n\n{COMMIT_INSTRUCTIONS}"
+  )
+}
+
+pub struct CommitPromptData {
+  pub branch_name: String,
+  pub issue_keys: Vec<String>,
+  pub scopes: String,
+  pub scope_matches: String,
+  pub is_nx_repo: bool,
+  pub affected_packages: Vec<String>,
+  pub code_owners: String,
+  pub diff: Diff,
+  pub file_categories: String,
+  pub symbol_changes: String,
+  pub breaking_changes: String,
+  pub dependency_changes: String,
+  pub recent_commits: Vec<String>,
+  pub change_analysis: String,
+}
+
+/// The changes and their context, the dynamic part following the system prompt
+pub async fn get_commit_user_prompt(data: CommitPromptData) -> Result<String> {
+  let CommitPromptData {
+    branch_name,
+    issue_keys,
+    scopes,
+    scope_matches,
+    is_nx_repo,
+    affected_packages,
+    code_owners,
+    diff,
+    file_categories,
+    symbol_changes,
+    breaking_changes,
+    dependency_changes,
+    recent_commits,
+    change_analysis,
+  } = data;
+
+  // Format recent commits
+  let recent_commits_str = format_recent_commits(recent_commits);
+
+  let affected_packages_str = if affected_packages.is_empty() {
+    "None".to_string()
+  } else {
+    affected_packages.join(", ")
+  };
+
+  let issue_keys_str = if issue_keys.is_empty() {
+    "None".to_string()
+  } else {
+    issue_keys.join(", ")
+  };
+
+  Ok(format!(
+    "Analyze the branch name, diff and scopes attached, to generate a conventional commit message.
 
 ```md
 Branch name: {}
+Issue keys: {}
 Scopes: {}
+Matching scopes:
+{}
 Is Nx Repository: {}
+Affected packages: {}
+Code owners:
+{}
 
+Diff summary:
+{}
 Diff of staged changes:
 {}
 
 Modified files:
 {}
 
+Symbol changes:
+{}
+
+Breaking changes:
+{}
+
+Dependency changes:
+{}
+
 Recent commits:
 {}
 
 Change analysis:
 {}
 ```
-
-ALWAYS RETURN COMMIT MESSAGE as STANDARD OUTPUT LIKE FOLLOWING AND NO EXPLANATION, NO INTRODUCTION, NO SUMMARY,
-JUST commit message, like following.
-
-<message-here>
-", branch_name, scopes, is_nx_repo, diff, grouped_files, recent_commits_str, change_analysis))
+",
+    branch_name,
+    issue_keys_str,
+    scopes,
+    scope_matches,
+    is_nx_repo,
+    affected_packages_str,
+    code_owners,
+    diff.stat(),
+    diff.render(),
+    file_categories,
+    symbol_changes,
+    breaking_changes,
+    dependency_changes,
+    recent_commits_str,
+    change_analysis
+  ))
 }
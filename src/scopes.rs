@@ -0,0 +1,431 @@
+use crate::config::ScopeValidation;
+use crate::file;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use regex::Regex;
+use serde::Deserialize;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScopesError {
+  #[error("Failed to parse scopes.toml: {0}")]
+  FailedToParse(#[from] toml::de::Error),
+  #[error("Invalid path glob {0} for scope {1}")]
+  InvalidGlob(String, String),
+}
+
+type Result<T> = std::result::Result<T, ScopesError>;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScopeDefinition {
+  pub name: String,
+  pub description: String,
+  /// Globs relative to the repository root, e.g. `src/client/**`
+  pub paths: Vec<String>,
+  pub aliases: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ScopesFile {
+  #[serde(default)]
+  scope: Vec<ScopeDefinition>,
+}
+
+/// A scope whose path globs match some of the staged files
+#[derive(Debug, Clone)]
+pub struct ScopeMatch {
+  pub name: String,
+  pub description: String,
+  pub files: Vec<String>,
+}
+
+/// Load scope definitions from `scopes.toml`, falling back to the plain one-per-line
+/// `scopes.txt`.
+pub async fn load_scopes(root_dir: &str) -> Result<Vec<ScopeDefinition>> {
+  if let Ok(content) = file::read_file(format!("{root_dir}/scopes.toml")).await {
+    let scopes: ScopesFile = toml::from_str(&content)?;
+    return Ok(scopes.scope);
+  }
+
+  let content = file::read_file(format!("{root_dir}/scopes.txt"))
+    .await
+    .unwrap_or_default();
+
+  let scopes = content
+    .lines()
+    .map(|line| line.trim())
+    .filter(|line| !line.is_empty())
+    .map(|name| ScopeDefinition {
+      name: name.to_string(),
+      ..Default::default()
+    })
+    .collect();
+
+  Ok(scopes)
+}
+
+/// Scopes whose path globs match at least one of the files, in definition order
+pub fn match_scopes(scopes: &[ScopeDefinition], files: &[String]) -> Result<Vec<ScopeMatch>> {
+  let mut matches = Vec::new();
+
+  for scope in scopes {
+    if scope.paths.is_empty() {
+      continue;
+    }
+
+    let globs = build_glob_set(scope)?;
+    let matched: Vec<String> = files
+      .iter()
+      .filter(|file| globs.is_match(file.as_str()))
+      .cloned()
+      .collect();
+
+    if !matched.is_empty() {
+      matches.push(ScopeMatch {
+        name: scope.name.clone(),
+        description: scope.description.clone(),
+        files: matched,
+      });
+    }
+  }
+
+  Ok(matches)
+}
+
+/// Format all known scopes for the prompt, e.g. `api (HTTP handlers), ui`
+pub fn format_scopes(scopes: &[ScopeDefinition]) -> String {
+  scopes
+    .iter()
+    .map(|scope| {
+      if scope.description.is_empty() {
+        scope.name.clone()
+      } else {
+        format!("{} ({})", scope.name, scope.description)
+      }
+    })
+    .collect::<Vec<String>>()
+    .join(", ")
+}
+
+/// Format the matching scopes with the files that caused each match
+pub fn format_scope_matches(matches: &[ScopeMatch]) -> String {
+  if matches.is_empty() {
+    return "None".to_string();
+  }
+
+  let mut result = String::new();
+  for scope_match in matches {
+    let name = if scope_match.description.is_empty() {
+      scope_match.name.clone()
+    } else {
+      format!("{} ({})", scope_match.name, scope_match.description)
+    };
+    result.push_str(&format!(
+      "- {}: matches {}\n",
+      name,
+      scope_match.files.join(", ")
+    ));
+  }
+
+  result
+}
+
+/// Check the scope chosen by the model against the scope definitions. Aliases are replaced
+/// with the canonical scope name, and `extra` names, like the affected workspace packages or
+/// `deps`, are accepted as they are. A scope that is unknown, or whose paths don't match the
+/// staged files while other scopes' paths do, is replaced with a close allowed scope, or removed
+/// when none is close; `ScopeValidation::Warn` keeps it. The returned warnings describe what
+/// was found and done.
+pub fn validate_scope(
+  message: &str,
+  scopes: &[ScopeDefinition],
+  matches: &[ScopeMatch],
+  extra: &[String],
+  validation: ScopeValidation,
+) -> (String, Vec<String>) {
+  let mut warnings = Vec::new();
+  if scopes.is_empty() {
+    return (message.to_string(), warnings);
+  }
+
+  let Some((start, end)) = find_subject_scope(message) else {
+    return (message.to_string(), warnings);
+  };
+  let chosen = &message[start..end];
+  if extra.iter().any(|name| name == chosen) {
+    return (message.to_string(), warnings);
+  }
+
+  // Scopes without paths can't be matched against the files, so they are always allowed
+  let allowed: Vec<&ScopeDefinition> = scopes
+    .iter()
+    .filter(|scope| {
+      scope.paths.is_empty() || matches.is_empty() || matches.iter().any(|m| m.name == scope.name)
+    })
+    .collect();
+  let known = scopes
+    .iter()
+    .find(|scope| scope.name == chosen || scope.aliases.iter().any(|alias| alias == chosen));
+
+  let problem = match known {
+    None => format!("Scope '{chosen}' is not defined in the scopes file"),
+    Some(scope) if !allowed.iter().any(|a| a.name == scope.name) => {
+      format!("Scope '{}' doesn't match the staged files", scope.name)
+    }
+    Some(scope) => {
+      let message = format!("{}{}{}", &message[..start], scope.name, &message[end..]);
+      return (message, warnings);
+    }
+  };
+
+  let replacement = match validation {
+    ScopeValidation::Warn => {
+      let names: Vec<&str> = allowed
+        .iter()
+        .map(|scope| scope.name.as_str())
+        .chain(extra.iter().map(String::as_str))
+        .collect();
+      warnings.push(format!("{problem}, expected one of: {}", names.join(", ")));
+      Some(known.map_or(chosen, |scope| scope.name.as_str()))
+    }
+    ScopeValidation::Repair => match closest_scope(chosen, &allowed, extra) {
+      Some(closest) => {
+        warnings.push(format!("{problem}, replaced it with '{closest}'"));
+        Some(closest)
+      }
+      None => {
+        warnings.push(format!("{problem}, removed it"));
+        None
+      }
+    },
+  };
+
+  // Without a scope the parentheses go too
+  let message = match replacement {
+    Some(scope) => format!("{}{}{}", &message[..start], scope, &message[end..]),
+    None => format!("{}{}", &message[..start - 1], &message[end + 1..]),
+  };
+  (message, warnings)
+}
+
+/// The allowed name or alias fewest edits away from `chosen`, the first one on a tie. Only
+/// typos count as close: at most 2 edits and a third of the length, so an unrelated scope is
+/// never picked.
+fn closest_scope<'a>(
+  chosen: &str,
+  allowed: &[&'a ScopeDefinition],
+  extra: &'a [String],
+) -> Option<&'a str> {
+  let chosen = chosen.to_lowercase();
+  let max_distance = 2.min(chosen.chars().count().div_ceil(3));
+
+  let scopes = allowed.iter().map(|scope| {
+    let names = std::iter::once(&scope.name).chain(&scope.aliases);
+    (scope.name.as_str(), names.collect::<Vec<&String>>())
+  });
+  let extra = extra.iter().map(|name| (name.as_str(), vec![name]));
+
+  scopes
+    .chain(extra)
+    .map(|(name, names)| {
+      let distance = names
+        .iter()
+        .map(|other| edit_distance(&chosen, &other.to_lowercase()))
+        .min()
+        .unwrap_or(usize::MAX);
+      (name, distance)
+    })
+    .filter(|&(_, distance)| distance <= max_distance)
+    .min_by_key(|&(_, distance)| distance)
+    .map(|(name, _)| name)
+}
+
+/// Levenshtein distance between two strings, counted in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+  for (i, a) in a.chars().enumerate() {
+    let mut current = vec![i + 1];
+    for (j, b) in b.iter().enumerate() {
+      let substitution = previous[j] + usize::from(a != *b);
+      current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+    }
+    previous = current;
+  }
+
+  previous[b.len()]
+}
+
+/// Byte range of the scope in a `type(scope): subject` line, allowing a leading `[prefix]`
+fn find_subject_scope(message: &str) -> Option<(usize, usize)> {
+  let regex = Regex::new(r"^(?:\[[^\]]*\]\s*)?[a-zA-Z]+\(([^)]+)\)!?:").ok()?;
+  let subject = message.lines().next()?;
+  let scope = regex.captures(subject)?.get(1)?;
+
+  Some((scope.start(), scope.end()))
+}
+
+fn build_glob_set(scope: &ScopeDefinition) -> Result<GlobSet> {
+  let mut builder = GlobSetBuilder::new();
+  for path in &scope.paths {
+    let glob = GlobBuilder::new(path)
+      .literal_separator(true)
+      .build()
+      .map_err(|_| ScopesError::InvalidGlob(path.clone(), scope.name.clone()))?;
+    builder.add(glob);
+  }
+
+  builder
+    .build()
+    .map_err(|_| ScopesError::InvalidGlob(scope.paths.join(", "), scope.name.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A scope owning `<name>/**`
+  fn scope(name: &str, aliases: &[&str]) -> ScopeDefinition {
+    ScopeDefinition {
+      name: name.to_string(),
+      aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+      paths: vec![format!("{name}/**")],
+      ..Default::default()
+    }
+  }
+
+  /// A scope from `scopes.txt`, without paths
+  fn plain(name: &str) -> ScopeDefinition {
+    ScopeDefinition {
+      name: name.to_string(),
+      ..Default::default()
+    }
+  }
+
+  fn matching(names: &[&str]) -> Vec<ScopeMatch> {
+    names
+      .iter()
+      .map(|name| ScopeMatch {
+        name: name.to_string(),
+        description: String::new(),
+        files: vec![format!("{name}/lib.rs")],
+      })
+      .collect()
+  }
+
+  #[test]
+  fn chosen_scopes_are_repaired_or_removed() {
+    let scopes = [
+      scope("client", &["api"]),
+      scope("docs", &[]),
+      scope("config", &[]),
+      plain("git-command"),
+    ];
+    let extra = ["deps".to_string(), "core".to_string()];
+    let cases: &[(&str, &[&str], &str, usize)] = &[
+      // (subject, matching scopes, repaired subject, warnings)
+      ("feat(client): add b", &["client"], "feat(client): add b", 0),
+      ("feat(api): add b", &["client"], "feat(client): add b", 0),
+      ("feat: add b", &["client"], "feat: add b", 0),
+      ("feat(clients): add b", &[], "feat(client): add b", 1),
+      ("feat(confg): add b", &[], "feat(config): add b", 1),
+      ("feat(apis)!: add b", &[], "feat(client)!: add b", 1),
+      (
+        "[ACME] fix(dcos): typo",
+        &["docs"],
+        "[ACME] fix(docs): typo",
+        1,
+      ),
+      // Scopes without paths stay allowed when path scopes match
+      (
+        "fix(git-command): quote",
+        &["docs"],
+        "fix(git-command): quote",
+        0,
+      ),
+      // Dependency changes and workspace packages
+      (
+        "build(deps): bump serde",
+        &["client"],
+        "build(deps): bump serde",
+        0,
+      ),
+      ("build(dep): bump serde", &[], "build(deps): bump serde", 1),
+      ("feat(core): add b", &["docs"], "feat(core): add b", 0),
+      // No close match
+      ("feat(parser): add b", &[], "feat: add b", 1),
+      ("docs(config)!: fix typo", &["docs"], "docs!: fix typo", 1),
+      ("fix(ui): typo", &[], "fix: typo", 1),
+    ];
+
+    for (subject, matched, expected, warnings) in cases {
+      let (message, found) = validate_scope(
+        subject,
+        &scopes,
+        &matching(matched),
+        &extra,
+        ScopeValidation::Repair,
+      );
+      assert_eq!(&message, expected, "{subject}");
+      assert_eq!(found.len(), *warnings, "{subject}: {found:?}");
+    }
+  }
+
+  #[test]
+  fn removed_scopes_are_reported() {
+    let scopes = [plain("client"), plain("args")];
+
+    let (message, warnings) = validate_scope(
+      "build(deps): bump serde",
+      &scopes,
+      &[],
+      &[],
+      ScopeValidation::Repair,
+    );
+    assert_eq!(message, "build: bump serde");
+    assert_eq!(
+      warnings,
+      ["Scope 'deps' is not defined in the scopes file, removed it"]
+    );
+  }
+
+  #[test]
+  fn warn_keeps_the_chosen_scope() {
+    let scopes = [scope("client", &["api"]), scope("docs", &[])];
+
+    let (message, warnings) = validate_scope(
+      "feat(cli): add b",
+      &scopes,
+      &[],
+      &["deps".to_string()],
+      ScopeValidation::Warn,
+    );
+    assert_eq!(message, "feat(cli): add b");
+    assert_eq!(
+      warnings,
+      ["Scope 'cli' is not defined in the scopes file, expected one of: client, docs, deps"]
+    );
+
+    let (message, warnings) = validate_scope(
+      "feat(api): add b",
+      &scopes,
+      &matching(&["docs"]),
+      &[],
+      ScopeValidation::Warn,
+    );
+    assert_eq!(message, "feat(client): add b");
+    assert_eq!(
+      warnings,
+      ["Scope 'client' doesn't match the staged files, expected one of: docs"]
+    );
+  }
+
+  #[test]
+  fn edit_distance_counts_characters() {
+    assert_eq!(edit_distance("docs", "docs"), 0);
+    assert_eq!(edit_distance("dcos", "docs"), 2);
+    assert_eq!(edit_distance("confg", "config"), 1);
+    assert_eq!(edit_distance("", "ui"), 2);
+    assert_eq!(edit_distance("ü", "u"), 1);
+  }
+}
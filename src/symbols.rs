@@ -0,0 +1,592 @@
+use crate::diff::{ChangeKind, Diff, FileDiff};
+use crate::git;
+use std::ops::Range;
+use tree_sitter::{Language, Node, Parser};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SourceLanguage {
+  Rust,
+  TypeScript,
+  Tsx,
+  JavaScript,
+  Python,
+  Go,
+}
+
+impl SourceLanguage {
+  fn from_path(path: &str) -> Option<Self> {
+    let extension = path.rsplit_once('.').map(|(_, ext)| ext)?;
+    match extension {
+      "rs" => Some(Self::Rust),
+      "ts" | "mts" | "cts" => Some(Self::TypeScript),
+      "tsx" => Some(Self::Tsx),
+      "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+      "py" => Some(Self::Python),
+      "go" => Some(Self::Go),
+      _ => None,
+    }
+  }
+
+  fn grammar(&self) -> Language {
+    match self {
+      Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+      Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+      Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+      Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+      Self::Python => tree_sitter_python::LANGUAGE.into(),
+      Self::Go => tree_sitter_go::LANGUAGE.into(),
+    }
+  }
+}
+
+/// A top-level declaration, or a method of a top-level type
+#[derive(Debug, Clone)]
+pub struct Symbol {
+  /// Short declaration kind, e.g. `fn`, `struct`, `class`
+  pub kind: String,
+  /// Qualified name, methods are written as `Type::method`
+  pub name: String,
+  /// Identifies the symbol within its file: the name, qualified with the implemented trait for
+  /// methods of Rust trait implementations, e.g. `<Display for Foo>::fmt`
+  pub key: String,
+  /// Byte range of the unqualified name within `text`
+  pub name_range: Option<Range<usize>>,
+  /// Source text of the declaration
+  pub text: String,
+  /// Declaration up to its body with whitespace collapsed, e.g. `pub fn run(args: &[String])`
+  pub signature: String,
+  /// Part of the public API: `pub` in Rust, `export`ed in TypeScript and JavaScript
+  pub public: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolChangeKind {
+  Added,
+  Removed,
+  Modified,
+  Renamed { from: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct SymbolChange {
+  pub kind: SymbolChangeKind,
+  pub symbol: Symbol,
+  /// The symbol before a modification or rename
+  pub previous: Option<Symbol>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileSymbolChanges {
+  pub path: String,
+  pub changes: Vec<SymbolChange>,
+}
+
+/// Compare the symbols of every supported file between `HEAD` and the index
+pub async fn extract_symbol_changes(diff: &Diff) -> Vec<FileSymbolChanges> {
+  let mut result = Vec::new();
+
+  for file in &diff.files {
+    if file.binary {
+      continue;
+    }
+    let Some(language) = SourceLanguage::from_path(&file.path) else {
+      continue;
+    };
+
+    let (old_source, new_source) = read_blobs(file).await;
+    let changes = compare_sources(language, &old_source, &new_source);
+    if !changes.is_empty() {
+      result.push(FileSymbolChanges {
+        path: file.path.clone(),
+        changes,
+      });
+    }
+  }
+
+  result
+}
+
+/// Old content from `HEAD` and new content from the index; missing blobs read as empty
+async fn read_blobs(file: &FileDiff) -> (String, String) {
+  let old_path = file.old_path.as_deref().unwrap_or(&file.path);
+
+  let old_source = match file.kind {
+    ChangeKind::Added | ChangeKind::Copied => String::new(),
+    _ => git::show_blob(&format!("HEAD:{old_path}"))
+      .await
+      .unwrap_or_default(),
+  };
+  let new_source = match file.kind {
+    ChangeKind::Deleted => String::new(),
+    _ => git::show_blob(&format!(":{}", file.path))
+      .await
+      .unwrap_or_default(),
+  };
+
+  (old_source, new_source)
+}
+
+fn compare_sources(language: SourceLanguage, old: &str, new: &str) -> Vec<SymbolChange> {
+  let old_symbols = parse_symbols(language, old);
+  let new_symbols = parse_symbols(language, new);
+  let old_keys = occurrence_keys(&old_symbols);
+  let new_keys = occurrence_keys(&new_symbols);
+
+  let mut changes = Vec::new();
+  let mut removed: Vec<&Symbol> = Vec::new();
+
+  for (old_symbol, old_key) in old_symbols.iter().zip(&old_keys) {
+    match new_keys.iter().position(|key| key == old_key) {
+      Some(index) if new_symbols[index].text != old_symbol.text => changes.push(SymbolChange {
+        kind: SymbolChangeKind::Modified,
+        symbol: new_symbols[index].clone(),
+        previous: Some(old_symbol.clone()),
+      }),
+      Some(_) => {}
+      None => removed.push(old_symbol),
+    }
+  }
+
+  for (new_symbol, new_key) in new_symbols.iter().zip(&new_keys) {
+    if old_keys.contains(new_key) {
+      continue;
+    }
+
+    // A removed symbol of the same kind with an identical body was renamed
+    let renamed_from = removed.iter().position(|old_symbol| {
+      old_symbol.kind == new_symbol.kind && strip_name(old_symbol) == strip_name(new_symbol)
+    });
+
+    match renamed_from {
+      Some(index) => {
+        let old_symbol = removed.remove(index);
+        changes.push(SymbolChange {
+          kind: SymbolChangeKind::Renamed {
+            from: old_symbol.name.clone(),
+          },
+          symbol: new_symbol.clone(),
+          previous: Some(old_symbol.clone()),
+        });
+      }
+      None => changes.push(SymbolChange {
+        kind: SymbolChangeKind::Added,
+        symbol: new_symbol.clone(),
+        previous: None,
+      }),
+    }
+  }
+
+  changes.extend(removed.into_iter().map(|symbol| SymbolChange {
+    kind: SymbolChangeKind::Removed,
+    symbol: symbol.clone(),
+    previous: None,
+  }));
+
+  changes
+}
+
+/// The key of every symbol with its occurrence among symbols of the same key, so duplicates,
+/// like `new` of overloads or repeated `From` impls, are paired in source order
+fn occurrence_keys(symbols: &[Symbol]) -> Vec<(&str, usize)> {
+  symbols
+    .iter()
+    .enumerate()
+    .map(|(index, symbol)| {
+      let occurrence = symbols[..index]
+        .iter()
+        .filter(|other| other.key == symbol.key)
+        .count();
+      (symbol.key.as_str(), occurrence)
+    })
+    .collect()
+}
+
+/// Declaration text without its name, for rename detection
+fn strip_name(symbol: &Symbol) -> String {
+  match &symbol.name_range {
+    Some(range) => format!(
+      "{}{}",
+      &symbol.text[..range.start],
+      &symbol.text[range.end..]
+    ),
+    None => symbol.text.clone(),
+  }
+}
+
+fn parse_symbols(language: SourceLanguage, source: &str) -> Vec<Symbol> {
+  if source.is_empty() {
+    return Vec::new();
+  }
+
+  let mut parser = Parser::new();
+  if parser.set_language(&language.grammar()).is_err() {
+    return Vec::new();
+  }
+  let Some(tree) = parser.parse(source, None) else {
+    return Vec::new();
+  };
+
+  let mut symbols = Vec::new();
+  let root = tree.root_node();
+  let mut cursor = root.walk();
+  for node in root.named_children(&mut cursor) {
+    collect_symbols(language, node, source, false, &mut symbols);
+  }
+
+  symbols
+}
+
+/// `exported` is set for declarations inside a TypeScript or JavaScript `export` statement
+fn collect_symbols(
+  language: SourceLanguage,
+  node: Node,
+  source: &str,
+  exported: bool,
+  symbols: &mut Vec<Symbol>,
+) {
+  let text = |node: Node| {
+    node
+      .utf8_text(source.as_bytes())
+      .unwrap_or_default()
+      .to_string()
+  };
+  let field = |node: Node, name: &str| node.child_by_field_name(name).map(text);
+  let public = |node: Node| match language {
+    SourceLanguage::Rust => is_rust_pub(node, source),
+    SourceLanguage::TypeScript | SourceLanguage::Tsx | SourceLanguage::JavaScript => exported,
+    SourceLanguage::Python | SourceLanguage::Go => false,
+  };
+  let push = |symbols: &mut Vec<Symbol>, kind: &str, name: Option<String>, node: Node| {
+    if let Some(name) = name {
+      let name_range = node
+        .child_by_field_name("name")
+        .map(|name| name.start_byte() - node.start_byte()..name.end_byte() - node.start_byte());
+      symbols.push(Symbol {
+        kind: kind.to_string(),
+        key: name.clone(),
+        name,
+        name_range,
+        text: text(node),
+        signature: signature(node, source),
+        public: public(node),
+      });
+    }
+  };
+
+  match (language, node.kind()) {
+    (SourceLanguage::Rust, kind) => match kind {
+      "function_item" => push(symbols, "fn", field(node, "name"), node),
+      "struct_item" => push(symbols, "struct", field(node, "name"), node),
+      "enum_item" => push(symbols, "enum", field(node, "name"), node),
+      "union_item" => push(symbols, "union", field(node, "name"), node),
+      "trait_item" => push(symbols, "trait", field(node, "name"), node),
+      "type_item" => push(symbols, "type", field(node, "name"), node),
+      "const_item" => push(symbols, "const", field(node, "name"), node),
+      "static_item" => push(symbols, "static", field(node, "name"), node),
+      "mod_item" => push(symbols, "mod", field(node, "name"), node),
+      "macro_definition" => push(symbols, "macro", field(node, "name"), node),
+      "impl_item" => {
+        let Some(type_name) = field(node, "type") else {
+          return;
+        };
+        // Methods of trait implementations follow the trait, they are not API of their own
+        let trait_name = field(node, "trait");
+        for method in child_declarations(node, "body", &["function_item"]) {
+          let Some(name) = field(method, "name") else {
+            continue;
+          };
+          push(symbols, "fn", Some(format!("{type_name}::{name}")), method);
+          if let Some(trait_name) = &trait_name
+            && let Some(symbol) = symbols.last_mut()
+          {
+            symbol.public = false;
+            symbol.key = format!("<{trait_name} for {type_name}>::{name}");
+          }
+        }
+      }
+      _ => {}
+    },
+    (SourceLanguage::TypeScript | SourceLanguage::Tsx | SourceLanguage::JavaScript, kind) => {
+      match kind {
+        "export_statement" => {
+          if let Some(declaration) = node.child_by_field_name("declaration") {
+            collect_symbols(language, declaration, source, true, symbols);
+          }
+        }
+        "function_declaration" | "generator_function_declaration" => {
+          push(symbols, "function", field(node, "name"), node)
+        }
+        "interface_declaration" => push(symbols, "interface", field(node, "name"), node),
+        "type_alias_declaration" => push(symbols, "type", field(node, "name"), node),
+        "enum_declaration" => push(symbols, "enum", field(node, "name"), node),
+        "lexical_declaration" | "variable_declaration" => {
+          let mut cursor = node.walk();
+          for declarator in node.named_children(&mut cursor) {
+            if declarator.kind() == "variable_declarator" {
+              push(symbols, "const", field(declarator, "name"), declarator);
+            }
+          }
+        }
+        "class_declaration" | "abstract_class_declaration" => {
+          let class_name = field(node, "name");
+          push(symbols, "class", class_name.clone(), node);
+          let Some(class_name) = class_name else {
+            return;
+          };
+          for method in child_declarations(node, "body", &["method_definition"]) {
+            let name = field(method, "name").map(|name| format!("{class_name}.{name}"));
+            push(symbols, "method", name, method);
+            if let Some(symbol) = symbols.last_mut() {
+              symbol.public = exported && !is_ts_private_member(method, source);
+            }
+          }
+        }
+        _ => {}
+      }
+    }
+    (SourceLanguage::Python, kind) => match kind {
+      "decorated_definition" => {
+        if let Some(definition) = node.child_by_field_name("definition") {
+          collect_symbols(language, definition, source, false, symbols);
+        }
+      }
+      "function_definition" => push(symbols, "def", field(node, "name"), node),
+      "class_definition" => {
+        let class_name = field(node, "name");
+        push(symbols, "class", class_name.clone(), node);
+        let Some(class_name) = class_name else {
+          return;
+        };
+        let methods = child_declarations(
+          node,
+          "body",
+          &["function_definition", "decorated_definition"],
+        );
+        for method in methods {
+          let method = match method.kind() {
+            "decorated_definition" => match method.child_by_field_name("definition") {
+              Some(definition) => definition,
+              None => continue,
+            },
+            _ => method,
+          };
+          let name = field(method, "name").map(|name| format!("{class_name}.{name}"));
+          push(symbols, "def", name, method);
+        }
+      }
+      _ => {}
+    },
+    (SourceLanguage::Go, kind) => match kind {
+      "function_declaration" => push(symbols, "func", field(node, "name"), node),
+      "method_declaration" => {
+        let receiver = node
+          .child_by_field_name("receiver")
+          .map(|receiver| go_receiver_type(&text(receiver)));
+        let name = match (receiver, field(node, "name")) {
+          (Some(receiver), Some(name)) => Some(format!("{receiver}.{name}")),
+          (None, name) => name,
+          (_, None) => None,
+        };
+        push(symbols, "func", name, node);
+      }
+      "type_declaration" => {
+        let mut cursor = node.walk();
+        for spec in node.named_children(&mut cursor) {
+          if spec.kind() == "type_spec" || spec.kind() == "type_alias" {
+            push(symbols, "type", field(spec, "name"), spec);
+          }
+        }
+      }
+      _ => {}
+    },
+  }
+}
+
+/// Named children of the `field` node (e.g. a class body) with one of the given kinds
+fn child_declarations<'a>(node: Node<'a>, field: &str, kinds: &[&str]) -> Vec<Node<'a>> {
+  let Some(body) = node.child_by_field_name(field) else {
+    return Vec::new();
+  };
+
+  let mut cursor = body.walk();
+  body
+    .named_children(&mut cursor)
+    .filter(|child| kinds.contains(&child.kind()))
+    .collect()
+}
+
+/// Text of the declaration before its body, so only the signature is compared
+fn signature(node: Node, source: &str) -> String {
+  let end = node
+    .child_by_field_name("body")
+    .map(|body| body.start_byte())
+    .unwrap_or(node.end_byte());
+  let text = source.get(node.start_byte()..end).unwrap_or_default();
+
+  text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Only plain `pub` is public API, `pub(crate)` and friends are not
+fn is_rust_pub(node: Node, source: &str) -> bool {
+  let mut cursor = node.walk();
+  node.children(&mut cursor).any(|child| {
+    child.kind() == "visibility_modifier" && child.utf8_text(source.as_bytes()) == Ok("pub")
+  })
+}
+
+/// `private` or `protected` members and `#private` names
+fn is_ts_private_member(node: Node, source: &str) -> bool {
+  let mut cursor = node.walk();
+  let hidden_modifier = node.children(&mut cursor).any(|child| {
+    child.kind() == "accessibility_modifier"
+      && matches!(
+        child.utf8_text(source.as_bytes()),
+        Ok("private") | Ok("protected")
+      )
+  });
+  let private_name = node
+    .child_by_field_name("name")
+    .is_some_and(|name| name.kind() == "private_property_identifier");
+
+  hidden_modifier || private_name
+}
+
+/// `(s *Server)` -> `Server`
+fn go_receiver_type(receiver: &str) -> String {
+  receiver
+    .trim_matches(|c| c == '(' || c == ')')
+    .split_whitespace()
+    .last()
+    .unwrap_or_default()
+    .trim_start_matches('*')
+    .split('[')
+    .next()
+    .unwrap_or_default()
+    .to_string()
+}
+
+/// Format the symbol changes for the prompt, one line per file
+pub fn format_symbol_changes(files: &[FileSymbolChanges]) -> String {
+  if files.is_empty() {
+    return "None".to_string();
+  }
+
+  let mut result = String::new();
+  for file in files {
+    let changes: Vec<String> = file
+      .changes
+      .iter()
+      .map(|change| {
+        let symbol = &change.symbol;
+        match &change.kind {
+          SymbolChangeKind::Added => format!("added {} {}", symbol.kind, symbol.name),
+          SymbolChangeKind::Removed => format!("removed {} {}", symbol.kind, symbol.name),
+          SymbolChangeKind::Modified => format!("modified {} {}", symbol.kind, symbol.name),
+          SymbolChangeKind::Renamed { from } => {
+            format!("renamed {} {} -> {}", symbol.kind, from, symbol.name)
+          }
+        }
+      })
+      .collect();
+    result.push_str(&format!("- {}: {}\n", file.path, changes.join(", ")));
+  }
+
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn changes(language: SourceLanguage, old: &str, new: &str) -> Vec<(SymbolChangeKind, String)> {
+    compare_sources(language, old, new)
+      .into_iter()
+      .map(|change| (change.kind, change.symbol.name))
+      .collect()
+  }
+
+  #[test]
+  fn added_removed_and_modified_symbols() {
+    let old = "fn keep() {}\nfn change() -> u8 { 1 }\nfn drop_me() {}\n";
+    let new = "fn keep() {}\nfn change() -> u8 { 2 }\nstruct Added;\n";
+
+    assert_eq!(
+      changes(SourceLanguage::Rust, old, new),
+      vec![
+        (SymbolChangeKind::Modified, "change".to_string()),
+        (SymbolChangeKind::Added, "Added".to_string()),
+        (SymbolChangeKind::Removed, "drop_me".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn renamed_symbols_keep_their_body() {
+    let old = "def load(path):\n    return open(path).read()\n";
+    let new = "def read_file(path):\n    return open(path).read()\n";
+
+    assert_eq!(
+      changes(SourceLanguage::Python, old, new),
+      vec![(
+        SymbolChangeKind::Renamed {
+          from: "load".to_string()
+        },
+        "read_file".to_string()
+      )]
+    );
+  }
+
+  #[test]
+  fn short_names_are_stripped_at_their_position() {
+    // The name `n` also occurs earlier in the text, inside the `fn` keyword
+    let old = "pub fn n(x: u8) -> u8 { x }\n";
+    let new = "pub fn m(x: u8) -> u8 { x }\n";
+
+    let symbol = &parse_symbols(SourceLanguage::Rust, old)[0];
+    assert_eq!(strip_name(symbol), "pub fn (x: u8) -> u8 { x }");
+    assert_eq!(
+      changes(SourceLanguage::Rust, old, new),
+      vec![(
+        SymbolChangeKind::Renamed {
+          from: "n".to_string()
+        },
+        "m".to_string()
+      )]
+    );
+  }
+
+  #[test]
+  fn trait_methods_of_the_same_name_are_told_apart() {
+    let old = "struct Id;
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, \"id\") }
+}
+impl fmt::Debug for Id {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, \"Id\") }
+}
+";
+    let new = old.replace("\"Id\"", "\"Id(..)\"");
+
+    let changes = compare_sources(SourceLanguage::Rust, old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].kind, SymbolChangeKind::Modified);
+    assert_eq!(changes[0].symbol.key, "<fmt::Debug for Id>::fmt");
+  }
+
+  #[test]
+  fn duplicate_names_are_paired_in_source_order() {
+    let old = "func (a A) Close() {}\nfunc (a A) Close() {}\n";
+    let new = "func (a A) Close() {}\nfunc (a A) Close() { a.flush() }\n";
+
+    assert_eq!(
+      changes(SourceLanguage::Go, old, new),
+      vec![(SymbolChangeKind::Modified, "A.Close".to_string())]
+    );
+  }
+
+  #[test]
+  fn unchanged_sources_have_no_changes() {
+    let source = "export class Greeter {\n  greet() { return 'hi' }\n}\n";
+    assert!(changes(SourceLanguage::TypeScript, source, source).is_empty());
+  }
+}
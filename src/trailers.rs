@@ -0,0 +1,84 @@
+use crate::config::TrailersConfig;
+use crate::git::{self, GitError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum TrailersError {
+  #[error("Unknown co-author {0}, expected an alias from the config or 'Name <email>'")]
+  UnknownCoAuthor(String),
+  #[error("Invalid trailer {0}, expected 'key=value'")]
+  InvalidTrailer(String),
+  #[error(transparent)]
+  GitError(#[from] GitError),
+}
+
+type Result<T> = std::result::Result<T, TrailersError>;
+
+pub struct TrailerOptions<'a> {
+  pub signoff: bool,
+  pub co_authors: Vec<&'a str>,
+  pub trailers: Vec<&'a str>,
+  /// Model to record in a `Generated-by` trailer
+  pub generated_by: Option<&'a str>,
+  /// Owners to list in a `Code-owners` trailer
+  pub code_owners: Vec<&'a str>,
+}
+
+/// Build the list of `Key: value` trailers from the command line options and config.
+pub async fn build_trailers(
+  options: &TrailerOptions<'_>,
+  config: &TrailersConfig,
+) -> Result<Vec<String>> {
+  let mut trailers = Vec::new();
+
+  for trailer in &options.trailers {
+    let (key, value) = trailer
+      .split_once('=')
+      .filter(|(key, value)| !key.trim().is_empty() && !value.trim().is_empty())
+      .ok_or_else(|| TrailersError::InvalidTrailer(trailer.to_string()))?;
+    trailers.push(format!("{}: {}", key.trim(), value.trim()));
+  }
+
+  for co_author in &options.co_authors {
+    let ident = resolve_co_author(co_author, config)?;
+    trailers.push(format!("Co-authored-by: {ident}"));
+  }
+
+  if options.signoff || config.signoff {
+    let ident = git::get_user_ident().await?;
+    trailers.push(format!("Signed-off-by: {ident}"));
+  }
+
+  if !options.code_owners.is_empty() {
+    trailers.push(format!("Code-owners: {}", options.code_owners.join(", ")));
+  }
+
+  if let Some(model) = options.generated_by {
+    trailers.push(format!("Generated-by: gen-commit ({model})"));
+  }
+
+  Ok(trailers)
+}
+
+/// Append the trailers to the commit message using `git interpret-trailers`.
+pub async fn apply_trailers(message: &str, trailers: &[String]) -> Result<String> {
+  if trailers.is_empty() {
+    return Ok(message.to_string());
+  }
+
+  Ok(git::interpret_trailers(message, trailers).await?)
+}
+
+fn resolve_co_author(co_author: &str, config: &TrailersConfig) -> Result<String> {
+  if let Some(ident) = config.co_authors.get(co_author) {
+    return Ok(ident.clone());
+  }
+
+  let is_ident = co_author
+    .find('<')
+    .is_some_and(|start| start > 0 && co_author.ends_with('>'));
+  if is_ident {
+    return Ok(co_author.to_string());
+  }
+
+  Err(TrailersError::UnknownCoAuthor(co_author.to_string()))
+}
@@ -0,0 +1,411 @@
+use crate::file;
+use crate::git;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde_json::Value;
+use yaml_rust2::YamlLoader;
+
+/// A package or project inside a monorepo, used as a candidate commit scope
+#[derive(Debug, Clone)]
+pub struct Project {
+  pub name: String,
+  /// Directories owned by the project, relative to the repository root
+  pub roots: Vec<String>,
+}
+
+/// Discover the packages of every workspace kind found in the repository root. When several
+/// tools describe the same directory, the first one wins in the order Nx, Cargo, pnpm/npm and
+/// Go.
+pub async fn detect_projects(root_dir: &str) -> Vec<Project> {
+  let mut projects: Vec<Project> = Vec::new();
+
+  let detected = [
+    detect_nx_projects(root_dir).await,
+    detect_cargo_projects(root_dir).await,
+    detect_js_projects(root_dir).await,
+    detect_go_projects(root_dir).await,
+  ];
+
+  for project in detected.into_iter().flatten() {
+    if !projects
+      .iter()
+      .any(|p| p.roots.first() == project.roots.first())
+    {
+      projects.push(project);
+    }
+  }
+
+  projects
+}
+
+/// Discover Nx projects from every `project.json` and every `package.json` with an `nx` section.
+/// The repository root is not used as a project root, as it would own every file outside the
+/// other projects; a root project only owns its `sourceRoot`.
+pub async fn detect_nx_projects(root_dir: &str) -> Vec<Project> {
+  let Ok(nx_config) = file::read_file(format!("{root_dir}/nx.json")).await else {
+    return Vec::new();
+  };
+  if serde_json::from_str::<Value>(&nx_config).is_err() {
+    return Vec::new();
+  }
+
+  let mut manifests = git::list_files(&["**/project.json", "**/package.json"])
+    .await
+    .unwrap_or_default();
+  // project.json takes precedence over package.json for the same directory, so it goes first
+  manifests.sort_by_key(|manifest| !manifest.ends_with("project.json"));
+
+  let mut projects: Vec<Project> = Vec::new();
+  for manifest in manifests {
+    let Ok(content) = file::read_file(format!("{root_dir}/{manifest}")).await else {
+      continue;
+    };
+    let Ok(json) = serde_json::from_str::<Value>(&content) else {
+      continue;
+    };
+
+    let dir = parent_dir(&manifest);
+    let project = if manifest.ends_with("project.json") {
+      nx_project_from_project_json(&json, dir)
+    } else {
+      nx_project_from_package_json(&json, dir)
+    };
+
+    if let Some(project) = project
+      && !projects
+        .iter()
+        .any(|p| p.roots.first() == project.roots.first())
+    {
+      projects.push(project);
+    }
+  }
+
+  projects
+}
+
+/// Names of the projects owning the given files. Each file belongs to the project with the
+/// longest matching root, files outside every project are ignored.
+pub fn affected_projects(projects: &[Project], files: &[String]) -> Vec<String> {
+  let mut affected: Vec<String> = Vec::new();
+
+  for file in files {
+    let owner = projects
+      .iter()
+      .flat_map(|project| project.roots.iter().map(move |root| (project, root)))
+      .filter(|(_, root)| is_within(file, root))
+      .max_by_key(|(_, root)| root.len())
+      .map(|(project, _)| project.name.clone());
+
+    if let Some(owner) = owner
+      && !affected.contains(&owner)
+    {
+      affected.push(owner);
+    }
+  }
+
+  affected.sort();
+  affected
+}
+
+/// Discover the members of the Cargo workspace declared in the root `Cargo.toml`.
+pub async fn detect_cargo_projects(root_dir: &str) -> Vec<Project> {
+  let Ok(content) = file::read_file(format!("{root_dir}/Cargo.toml")).await else {
+    return Vec::new();
+  };
+  let Ok(manifest) = toml::from_str::<toml::Table>(&content) else {
+    return Vec::new();
+  };
+  let Some(workspace) = manifest.get("workspace").and_then(|w| w.as_table()) else {
+    return Vec::new();
+  };
+
+  let string_list = |key: &str| -> Vec<String> {
+    workspace
+      .get(key)
+      .and_then(|v| v.as_array())
+      .map(|items| {
+        items
+          .iter()
+          .filter_map(|item| item.as_str().map(normalize_path))
+          .collect()
+      })
+      .unwrap_or_default()
+  };
+  let members = string_list("members");
+  let exclude = string_list("exclude");
+
+  let mut projects = Vec::new();
+  for dir in matching_manifest_dirs("Cargo.toml", &members, &exclude).await {
+    let Ok(content) = file::read_file(format!("{root_dir}/{dir}/Cargo.toml")).await else {
+      continue;
+    };
+    let name = toml::from_str::<toml::Table>(&content)
+      .ok()
+      .and_then(|manifest| {
+        manifest
+          .get("package")?
+          .get("name")?
+          .as_str()
+          .map(|name| name.to_string())
+      })
+      .or_else(|| dir_name(&dir));
+
+    if let Some(name) = name {
+      projects.push(Project {
+        name,
+        roots: vec![dir],
+      });
+    }
+  }
+
+  projects
+}
+
+/// Discover the packages of a pnpm workspace (`pnpm-workspace.yaml`) or an npm/yarn/bun
+/// workspace (`workspaces` in the root `package.json`). Turborepo has no package list of its
+/// own and runs on top of these workspaces, so a `turbo.json` without them is only reported.
+pub async fn detect_js_projects(root_dir: &str) -> Vec<Project> {
+  let mut patterns: Vec<String> = Vec::new();
+
+  if let Ok(content) = file::read_file(format!("{root_dir}/pnpm-workspace.yaml")).await
+    && let Ok(documents) = YamlLoader::load_from_str(&content)
+    && let Some(packages) = documents.first().and_then(|yaml| yaml["packages"].as_vec())
+  {
+    patterns.extend(
+      packages
+        .iter()
+        .filter_map(|p| p.as_str().map(|p| p.to_string())),
+    );
+  }
+
+  if let Ok(content) = file::read_file(format!("{root_dir}/package.json")).await
+    && let Ok(json) = serde_json::from_str::<Value>(&content)
+    && let Some(workspaces) = json.get("workspaces")
+  {
+    // Either a list of globs or `{ "packages": [...] }` as used by yarn
+    let workspaces = workspaces.get("packages").unwrap_or(workspaces);
+    if let Some(items) = workspaces.as_array() {
+      patterns.extend(
+        items
+          .iter()
+          .filter_map(|p| p.as_str().map(|p| p.to_string())),
+      );
+    }
+  }
+
+  if patterns.is_empty() {
+    if let Ok(content) = file::read_file(format!("{root_dir}/turbo.json")).await
+      && serde_json::from_str::<Value>(&content).is_ok()
+    {
+      println!(
+        "[OUTPUT] Warning: turbo.json found without pnpm-workspace.yaml or package.json workspaces, its packages are not detected"
+      );
+    }
+    return Vec::new();
+  }
+
+  let (exclude, members): (Vec<String>, Vec<String>) = patterns
+    .into_iter()
+    .partition(|pattern| pattern.starts_with('!'));
+  let members: Vec<String> = members.iter().map(|p| normalize_path(p)).collect();
+  let exclude: Vec<String> = exclude
+    .iter()
+    .map(|p| normalize_path(p.trim_start_matches('!')))
+    .collect();
+
+  let mut projects = Vec::new();
+  for dir in matching_manifest_dirs("package.json", &members, &exclude).await {
+    let Ok(content) = file::read_file(format!("{root_dir}/{dir}/package.json")).await else {
+      continue;
+    };
+    let name = serde_json::from_str::<Value>(&content)
+      .ok()
+      .and_then(|json| json.get("name")?.as_str().map(|name| name.to_string()))
+      // `@acme/core` is used as `core`, the npm scope is noise in a commit scope
+      .map(|name| {
+        name
+          .rsplit_once('/')
+          .map(|(_, name)| name.to_string())
+          .unwrap_or(name)
+      })
+      .or_else(|| dir_name(&dir));
+
+    if let Some(name) = name {
+      projects.push(Project {
+        name,
+        roots: vec![dir],
+      });
+    }
+  }
+
+  projects
+}
+
+/// Discover the modules listed in `go.work`.
+pub async fn detect_go_projects(root_dir: &str) -> Vec<Project> {
+  let Ok(content) = file::read_file(format!("{root_dir}/go.work")).await else {
+    return Vec::new();
+  };
+
+  let mut projects = Vec::new();
+  for dir in parse_go_work_uses(&content) {
+    let module = file::read_file(format!("{root_dir}/{dir}/go.mod"))
+      .await
+      .ok()
+      .and_then(|go_mod| {
+        go_mod
+          .lines()
+          .find_map(|line| line.trim().strip_prefix("module "))
+          .map(|module| module.trim().trim_matches('"').to_string())
+      });
+
+    // `github.com/acme/api` is used as `api`
+    let name = module
+      .as_deref()
+      .and_then(|module| module.rsplit('/').next())
+      .map(|name| name.to_string())
+      .or_else(|| dir_name(&dir));
+
+    if let Some(name) = name {
+      projects.push(Project {
+        name,
+        roots: vec![dir],
+      });
+    }
+  }
+
+  projects
+}
+
+/// Parse both `use ./dir` and `use ( ./a ./b )` directives
+fn parse_go_work_uses(content: &str) -> Vec<String> {
+  let mut dirs = Vec::new();
+  let mut in_block = false;
+
+  for line in content.lines() {
+    let line = line.split("//").next().unwrap_or_default().trim();
+
+    if in_block {
+      if line == ")" {
+        in_block = false;
+      } else if !line.is_empty() {
+        dirs.push(normalize_path(line.trim_matches('"')));
+      }
+      continue;
+    }
+
+    // `use` followed by whitespace or `(`, not a directive like `user`
+    if let Some(rest) = line.strip_prefix("use")
+      && rest.starts_with(|c: char| c.is_whitespace() || c == '(')
+    {
+      let rest = rest.trim();
+      if rest == "(" {
+        in_block = true;
+      } else if !rest.is_empty() {
+        dirs.push(normalize_path(rest.trim_matches('"')));
+      }
+    }
+  }
+
+  dirs
+}
+
+/// Directories containing `manifest` that match one of the member globs and none of the
+/// exclude globs
+async fn matching_manifest_dirs(
+  manifest: &str,
+  members: &[String],
+  exclude: &[String],
+) -> Vec<String> {
+  let (Some(members), Some(exclude)) = (build_glob_set(members), build_glob_set(exclude)) else {
+    return Vec::new();
+  };
+  if members.is_empty() {
+    return Vec::new();
+  }
+
+  git::list_files(&[&format!("**/{manifest}")])
+    .await
+    .unwrap_or_default()
+    .iter()
+    .map(|path| parent_dir(path).to_string())
+    .filter(|dir| !dir.is_empty() && members.is_match(dir) && !exclude.is_match(dir))
+    .collect()
+}
+
+fn build_glob_set(patterns: &[String]) -> Option<GlobSet> {
+  let mut builder = GlobSetBuilder::new();
+  for pattern in patterns {
+    // `*` stays within one directory, as in Cargo and package manager workspace globs
+    let glob = GlobBuilder::new(pattern)
+      .literal_separator(true)
+      .build()
+      .ok()?;
+    builder.add(glob);
+  }
+  builder.build().ok()
+}
+
+fn nx_project_from_project_json(json: &Value, dir: &str) -> Option<Project> {
+  let name = json
+    .get("name")
+    .and_then(Value::as_str)
+    .map(|name| name.to_string())
+    .or_else(|| dir_name(dir))?;
+
+  let mut roots = vec![
+    json
+      .get("root")
+      .and_then(Value::as_str)
+      .map(normalize_path)
+      .unwrap_or_else(|| dir.to_string()),
+  ];
+  if let Some(source_root) = json.get("sourceRoot").and_then(Value::as_str) {
+    roots.push(normalize_path(source_root));
+  }
+  roots.retain(|root| !root.is_empty());
+
+  (!roots.is_empty()).then_some(Project { name, roots })
+}
+
+fn nx_project_from_package_json(json: &Value, dir: &str) -> Option<Project> {
+  let nx = json.get("nx").filter(|_| !dir.is_empty())?;
+  let name = nx
+    .get("name")
+    .or_else(|| json.get("name"))
+    .and_then(Value::as_str)
+    .map(|name| name.to_string())
+    .or_else(|| dir_name(dir))?;
+
+  Some(Project {
+    name,
+    roots: vec![dir.to_string()],
+  })
+}
+
+fn parent_dir(path: &str) -> &str {
+  path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("")
+}
+
+fn dir_name(dir: &str) -> Option<String> {
+  dir
+    .rsplit('/')
+    .next()
+    .filter(|name| !name.is_empty())
+    .map(|name| name.to_string())
+}
+
+/// Paths relative to the repository root, where the root itself is an empty path
+fn normalize_path(path: &str) -> String {
+  let path = path.trim_start_matches("./").trim_end_matches('/');
+  if path == "." {
+    return String::new();
+  }
+  path.to_string()
+}
+
+fn is_within(file: &str, root: &str) -> bool {
+  root.is_empty()
+    || file
+      .strip_prefix(root)
+      .is_some_and(|rest| rest.starts_with('/'))
+}
@@ -0,0 +1,192 @@
+mod common;
+
+use common::{StandIn, TestRepo, stderr, stdout};
+use serde_json::json;
+
+fn openai_stand_in() -> StandIn {
+  let message = json!({
+    "type": "chore",
+    "scope": null,
+    "breaking": false,
+    "subject": "update a",
+    "body": [],
+    "footers": [],
+    "prefix": null,
+  });
+
+  StandIn::start(vec![json!({
+    "output": [{
+      "type": "message",
+      "content": [{ "type": "output_text", "text": message.to_string() }],
+    }],
+    "usage": { "input_tokens": 10, "output_tokens": 5, "total_tokens": 15 },
+  })])
+}
+
+fn staged_repo(config: &str) -> TestRepo {
+  let repo = TestRepo::new();
+  repo.commit(&[("a.txt", "a\n")], "chore: init");
+  repo.stage(&[("a.txt", "b\n")]);
+  repo.write(".gen-commit.toml", config);
+  repo
+}
+
+#[test]
+fn key_command_supplies_the_api_key() {
+  let repo = staged_repo("[providers.openai]\nkey_command = \"echo sk-from-command\"\n");
+  let stand_in = openai_stand_in();
+
+  let output = repo
+    .gen_commit()
+    .env("OPENAI_BASE_URL", stand_in.url())
+    .args([
+      "--model",
+      "openai:gpt-test",
+      "--no-analysis",
+      "--no-cache",
+      "--dry-run",
+    ])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert_eq!(
+    stand_in.requests()[0].header("authorization"),
+    Some("Bearer sk-from-command")
+  );
+}
+
+#[test]
+fn key_file_supplies_the_api_key() {
+  let repo = staged_repo("[providers.openai]\nkey_file = \"openai.key\"\n");
+  repo.write("openai.key", "sk-from-file\n");
+  let stand_in = openai_stand_in();
+
+  let output = repo
+    .gen_commit()
+    .env("OPENAI_BASE_URL", stand_in.url())
+    .args([
+      "--model",
+      "openai:gpt-test",
+      "--no-analysis",
+      "--no-cache",
+      "--dry-run",
+    ])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert_eq!(
+    stand_in.requests()[0].header("authorization"),
+    Some("Bearer sk-from-file")
+  );
+}
+
+#[test]
+fn environment_variable_takes_precedence() {
+  let repo = staged_repo("[providers.openai]\nkey_command = \"exit 1\"\n");
+  let stand_in = openai_stand_in();
+
+  let output = repo
+    .gen_commit()
+    .env("OPENAI_API_KEY", "sk-from-env")
+    .env("OPENAI_BASE_URL", stand_in.url())
+    .args([
+      "--model",
+      "openai:gpt-test",
+      "--no-analysis",
+      "--no-cache",
+      "--dry-run",
+    ])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert_eq!(
+    stand_in.requests()[0].header("authorization"),
+    Some("Bearer sk-from-env")
+  );
+}
+
+#[test]
+fn missing_key_names_the_provider() {
+  let repo = staged_repo("");
+
+  let output = repo
+    .gen_commit()
+    .args([
+      "--model",
+      "openai:gpt-test",
+      "--no-analysis",
+      "--no-cache",
+      "--dry-run",
+    ])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(!output.status.success());
+  let stderr = stderr(&output);
+  assert!(stderr.contains("No API key found for openai"), "{stderr}");
+  assert!(stderr.contains("OPENAI_API_KEY"), "{stderr}");
+  assert!(stderr.contains("gen-commit auth login openai"), "{stderr}");
+  assert!(!stderr.contains("ANTHROPIC"), "{stderr}");
+}
+
+#[test]
+fn failing_key_command_is_reported() {
+  let repo = staged_repo("[providers.anthropic]\nkey_command = \"echo locked >&2; exit 3\"\n");
+
+  let output = repo
+    .gen_commit()
+    .args([
+      "--model",
+      "anthropic:claude-test",
+      "--no-analysis",
+      "--no-cache",
+      "--dry-run",
+    ])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(!output.status.success());
+  let stderr = stderr(&output);
+  assert!(
+    stderr.contains("Key command `echo locked >&2; exit 3` failed: locked"),
+    "{stderr}"
+  );
+}
+
+#[test]
+fn status_shows_masked_keys_and_their_source() {
+  let repo = staged_repo("[providers.google]\nkey_command = \"echo gemini-key-0123456789\"\n");
+
+  let output = repo
+    .gen_commit()
+    .env("ANTHROPIC_API_KEY", "sk-ant-secret-abcd")
+    .args(["auth", "status"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  let stdout = stdout(&output);
+  assert!(stdout.contains("anthropic: sk-...abcd from environment variable ANTHROPIC_API_KEY"));
+  assert!(stdout.contains("google: gem...6789 from key command `echo gemini-key-0123456789`"));
+  assert!(stdout.contains("openai: not set"));
+  assert!(!stdout.contains("sk-ant-secret-abcd"));
+}
+
+#[test]
+fn status_works_outside_a_repository() {
+  let repo = TestRepo::new();
+  let outside = tempfile::TempDir::new().expect("create dir");
+
+  let output = repo
+    .gen_commit()
+    .current_dir(outside.path())
+    .args(["auth", "status"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(stdout(&output).contains("azure: not set"));
+}
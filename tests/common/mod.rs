@@ -0,0 +1,293 @@
+//! Helpers shared by the integration tests: temporary git repositories, mock provider fixtures
+//! and a local HTTP stand-in for the provider APIs.
+
+#![allow(dead_code)]
+
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+/// Environment variables of the developer's machine that would change the binary's behavior
+const ISOLATED_ENV: [&str; 21] = [
+  "GC_DEFAULT_MODEL",
+  "GC_IGNORE_LIST",
+  "GC_CONFIG",
+  "ANTHROPIC_API_KEY",
+  "ANTHROPIC_BASE_URL",
+  "OPENAI_API_KEY",
+  "OPENAI_BASE_URL",
+  "GEMINI_API_KEY",
+  "GEMINI_BASE_URL",
+  "AZURE_OPENAI_API_KEY",
+  "AZURE_OPENAI_ENDPOINT",
+  "AZURE_OPENAI_RESOURCE",
+  "AZURE_OPENAI_API_VERSION",
+  "HTTPS_PROXY",
+  "https_proxy",
+  "HTTP_PROXY",
+  "http_proxy",
+  "ALL_PROXY",
+  "all_proxy",
+  "GIT_DIR",
+  "GIT_WORK_TREE",
+];
+
+/// A git repository in a temporary directory, with its own home so no user config is read
+pub struct TestRepo {
+  dir: TempDir,
+  home: TempDir,
+}
+
+impl TestRepo {
+  pub fn new() -> Self {
+    let repo = Self {
+      dir: TempDir::new().expect("create repo dir"),
+      home: TempDir::new().expect("create home dir"),
+    };
+    repo.git(&["init", "-q", "-b", "main"]);
+    repo.git(&["config", "user.name", "Test User"]);
+    repo.git(&["config", "user.email", "test@example.com"]);
+    repo.git(&["config", "commit.gpgsign", "false"]);
+    repo
+  }
+
+  pub fn path(&self) -> &Path {
+    self.dir.path()
+  }
+
+  pub fn write(&self, path: &str, content: &str) {
+    let path = self.path().join(path);
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent).expect("create parent dirs");
+    }
+    std::fs::write(path, content).expect("write file");
+  }
+
+  /// Run git in the repository and return its stdout
+  pub fn git(&self, args: &[&str]) -> String {
+    let output = Command::new("git")
+      .args(args)
+      .current_dir(self.path())
+      .env("HOME", self.home.path())
+      .env("GIT_CONFIG_NOSYSTEM", "1")
+      .output()
+      .expect("run git");
+    assert!(
+      output.status.success(),
+      "git {args:?} failed: {}",
+      String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).to_string()
+  }
+
+  /// Write and commit files, so later changes have a history to diff against
+  pub fn commit(&self, files: &[(&str, &str)], message: &str) {
+    for (path, content) in files {
+      self.write(path, content);
+    }
+    self.git(&["add", "-A"]);
+    self.git(&["commit", "-q", "-m", message]);
+  }
+
+  /// Write and stage files
+  pub fn stage(&self, files: &[(&str, &str)]) {
+    for (path, content) in files {
+      self.write(path, content);
+    }
+    self.git(&["add", "-A"]);
+  }
+
+  /// `git log -1 --format=%B` of the last commit
+  pub fn last_commit_message(&self) -> String {
+    self.git(&["log", "-1", "--format=%B"]).trim().to_string()
+  }
+
+  /// The binary with the repository as working directory and an isolated environment
+  pub fn gen_commit(&self) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_gen-commit"));
+    command
+      .current_dir(self.path())
+      .env("HOME", self.home.path())
+      .env("XDG_CONFIG_HOME", self.home.path().join(".config"))
+      .env("GIT_CONFIG_NOSYSTEM", "1")
+      .env("NO_PROXY", "127.0.0.1,localhost")
+      // Keep the developer's keyring out of the tests
+      .env(
+        "DBUS_SESSION_BUS_ADDRESS",
+        "unix:path=/nonexistent/gen-commit-test-bus",
+      );
+    for var in ISOLATED_ENV {
+      command.env_remove(var);
+    }
+    command
+  }
+
+  /// A `mock:` fixture answering with `responses` in order; requests are recorded next to it
+  pub fn mock_fixture(&self, responses: &[Value]) -> MockFixture {
+    let path = self.home.path().join("fixture.json");
+    let record = self.home.path().join("requests.jsonl");
+    let fixture = json!({
+      "responses": responses,
+      "record": record,
+    });
+    std::fs::write(&path, fixture.to_string()).expect("write fixture");
+
+    MockFixture { path, record }
+  }
+}
+
+pub struct MockFixture {
+  path: PathBuf,
+  record: PathBuf,
+}
+
+impl MockFixture {
+  /// Value for `--model`
+  pub fn model(&self) -> String {
+    format!("mock:{}", self.path.display())
+  }
+
+  /// Requests the mock provider received, in order
+  pub fn requests(&self) -> Vec<Value> {
+    std::fs::read_to_string(&self.record)
+      .unwrap_or_default()
+      .lines()
+      .map(|line| serde_json::from_str(line).expect("recorded request is JSON"))
+      .collect()
+  }
+}
+
+/// A fixture response carrying a structured commit message
+pub fn commit_response(r#type: &str, scope: Option<&str>, subject: &str) -> Value {
+  let message = json!({
+    "type": r#type,
+    "scope": scope,
+    "breaking": false,
+    "subject": subject,
+    "body": [],
+    "footers": [],
+    "prefix": null,
+  });
+
+  json!({
+    "message": message.to_string(),
+    "usage": { "input_tokens": 100, "output_tokens": 20 },
+  })
+}
+
+pub fn stdout(output: &Output) -> String {
+  String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+pub fn stderr(output: &Output) -> String {
+  String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+/// A request received by [`StandIn`]
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+  pub path: String,
+  /// Header names are lowercased
+  pub headers: Vec<(String, String)>,
+  pub body: Value,
+}
+
+impl RecordedRequest {
+  pub fn header(&self, name: &str) -> Option<&str> {
+    self
+      .headers
+      .iter()
+      .find(|(key, _)| key == name)
+      .map(|(_, value)| value.as_str())
+  }
+}
+
+/// Local HTTP server standing in for a provider API. It answers every request with the next
+/// of the given JSON bodies, the last one repeating, and records what it received.
+pub struct StandIn {
+  url: String,
+  requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl StandIn {
+  pub fn start(responses: Vec<Value>) -> Self {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind stand-in");
+    let url = format!(
+      "http://{}",
+      listener.local_addr().expect("stand-in address")
+    );
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let recorded = Arc::clone(&requests);
+    std::thread::spawn(move || {
+      for (index, stream) in listener.incoming().enumerate() {
+        let Ok(mut stream) = stream else {
+          continue;
+        };
+        let Some(request) = read_request(&mut stream) else {
+          continue;
+        };
+        recorded.lock().expect("lock requests").push(request);
+
+        let body = responses
+          .get(index)
+          .or(responses.last())
+          .map(|body| body.to_string())
+          .unwrap_or_default();
+        let response = format!(
+          "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+          body.len()
+        );
+        let _ = stream.write_all(response.as_bytes());
+      }
+    });
+
+    Self { url, requests }
+  }
+
+  pub fn url(&self) -> &str {
+    &self.url
+  }
+
+  pub fn requests(&self) -> Vec<RecordedRequest> {
+    self.requests.lock().expect("lock requests").clone()
+  }
+}
+
+fn read_request(stream: &mut std::net::TcpStream) -> Option<RecordedRequest> {
+  let mut reader = BufReader::new(stream);
+
+  let mut request_line = String::new();
+  reader.read_line(&mut request_line).ok()?;
+  let path = request_line.split_whitespace().nth(1)?.to_string();
+
+  let mut headers = Vec::new();
+  loop {
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let line = line.trim_end();
+    if line.is_empty() {
+      break;
+    }
+    let (key, value) = line.split_once(':')?;
+    headers.push((key.trim().to_lowercase(), value.trim().to_string()));
+  }
+
+  let length = headers
+    .iter()
+    .find(|(key, _)| key == "content-length")
+    .and_then(|(_, value)| value.parse().ok())
+    .unwrap_or(0);
+  let mut body = vec![0; length];
+  reader.read_exact(&mut body).ok()?;
+
+  Some(RecordedRequest {
+    path,
+    headers,
+    body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+  })
+}
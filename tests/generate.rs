@@ -0,0 +1,286 @@
+mod common;
+
+use common::{TestRepo, commit_response, stderr, stdout};
+use serde_json::json;
+
+#[test]
+fn dry_run_prints_the_assembled_message() {
+  let repo = TestRepo::new();
+  repo.commit(&[("README.md", "# Demo\n")], "docs: add readme");
+  repo.stage(&[(
+    "src/greeting.rs",
+    "pub fn greet() -> &'static str {\n  \"hi\"\n}\n",
+  )]);
+  let fixture = repo.mock_fixture(&[
+    json!({ "message": "Adds a greeting function." }),
+    commit_response("feat", Some("greeting"), "add greet function"),
+  ]);
+
+  let output = repo
+    .gen_commit()
+    .args(["--model", &fixture.model(), "--dry-run", "--no-cache"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(stdout(&output).contains("feat(greeting): add greet function"));
+  assert_eq!(repo.git(&["rev-list", "--count", "HEAD"]).trim(), "1");
+
+  let requests = fixture.requests();
+  assert_eq!(requests.len(), 2, "analysis and generation requests");
+  assert!(
+    requests[0]["user"]
+      .as_str()
+      .unwrap()
+      .contains("src/greeting.rs")
+  );
+  assert!(requests[0].get("schema").is_none());
+
+  let user = requests[1]["user"].as_str().unwrap();
+  assert!(user.contains("Branch name: main"));
+  assert!(user.contains("+pub fn greet()"));
+  assert!(user.contains("Adds a greeting function."));
+  assert!(user.contains("docs: add readme"));
+  assert_eq!(requests[1]["schema"]["type"], "object");
+}
+
+#[test]
+fn yes_commits_the_generated_message() {
+  let repo = TestRepo::new();
+  repo.commit(&[("src/lib.rs", "pub fn parse() {}\n")], "feat: add parser");
+  repo.stage(&[(
+    "src/lib.rs",
+    "pub fn parse() {\n  // handle empty input\n}\n",
+  )]);
+  let fixture = repo.mock_fixture(&[commit_response("fix", None, "Handle empty input.")]);
+
+  let output = repo
+    .gen_commit()
+    .args(["--model", &fixture.model(), "--no-analysis", "--no-cache"])
+    .args(["--yes", "--no-edit"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(stdout(&output).contains("Successfully committed!"));
+  assert_eq!(repo.last_commit_message(), "fix: Handle empty input");
+  assert_eq!(fixture.requests().len(), 1, "analysis is skipped");
+}
+
+#[test]
+fn issue_keys_from_the_branch_are_added_as_footers() {
+  let repo = TestRepo::new();
+  repo.commit(&[("app.txt", "v1\n")], "chore: init");
+  repo.git(&["checkout", "-q", "-b", "feature/PROJ-42-login"]);
+  repo.stage(&[("app.txt", "v2\n")]);
+  let fixture = repo.mock_fixture(&[commit_response("feat", None, "add login")]);
+
+  let output = repo
+    .gen_commit()
+    .args(["--model", &fixture.model(), "--no-analysis", "--no-cache"])
+    .args(["--yes", "--no-edit"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert_eq!(
+    repo.last_commit_message(),
+    "feat: add login\n\nRefs: PROJ-42"
+  );
+
+  let user = fixture.requests()[0]["user"].as_str().unwrap().to_string();
+  assert!(user.contains("Branch name: feature/PROJ-42-login"));
+  assert!(user.contains("Issue keys: PROJ-42"));
+}
+
+#[test]
+fn dependency_changes_are_listed_in_the_prompt() {
+  let repo = TestRepo::new();
+  repo.commit(
+    &[(
+      "Cargo.toml",
+      "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1.0\"\n",
+    )],
+    "chore: init",
+  );
+  repo.stage(&[(
+    "Cargo.toml",
+    "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1.0\"\nregex = \"1.11\"\n",
+  )]);
+  let fixture = repo.mock_fixture(&[commit_response("build", Some("deps"), "add regex 1.11")]);
+
+  let output = repo
+    .gen_commit()
+    .args([
+      "--model",
+      &fixture.model(),
+      "--no-analysis",
+      "--no-cache",
+      "--dry-run",
+    ])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(stdout(&output).contains("build(deps): add regex 1.11"));
+
+  let user = fixture.requests()[0]["user"].as_str().unwrap().to_string();
+  assert!(user.contains("Add regex 1.11"));
+}
+
+#[test]
+fn cached_responses_skip_the_provider() {
+  let repo = TestRepo::new();
+  repo.commit(&[("a.txt", "a\n")], "chore: init");
+  repo.stage(&[("a.txt", "b\n")]);
+  repo.write(".gen-commit.toml", "[cache]\ngeneration = true\n");
+  let fixture = repo.mock_fixture(&[commit_response("chore", None, "update a")]);
+
+  for _ in 0..2 {
+    let output = repo
+      .gen_commit()
+      .args(["--model", &fixture.model(), "--no-analysis", "--dry-run"])
+      .output()
+      .expect("run gen-commit");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("chore: update a"));
+  }
+
+  assert_eq!(fixture.requests().len(), 1);
+}
+
+#[test]
+fn missing_fixture_fails() {
+  let repo = TestRepo::new();
+  repo.stage(&[("a.txt", "a\n")]);
+
+  let output = repo
+    .gen_commit()
+    .args([
+      "--model",
+      "mock:does-not-exist.json",
+      "--no-analysis",
+      "--dry-run",
+    ])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(!output.status.success());
+  assert!(stderr(&output).contains("Failed to read fixture does-not-exist.json"));
+}
+
+#[test]
+fn nothing_staged_exits_without_calling_the_provider() {
+  let repo = TestRepo::new();
+  repo.commit(&[("a.txt", "a\n")], "chore: init");
+  let fixture = repo.mock_fixture(&[commit_response("chore", None, "nothing")]);
+
+  let output = repo
+    .gen_commit()
+    .args(["--model", &fixture.model(), "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(fixture.requests().is_empty());
+  assert!(!stdout(&output).contains("Generated commit message"));
+}
+
+#[test]
+fn reasoning_effort_is_passed_to_the_provider() {
+  let repo = TestRepo::new();
+  repo.commit(&[("a.txt", "a\n")], "chore: init");
+  repo.stage(&[("a.txt", "b\n")]);
+  let fixture = repo.mock_fixture(&[commit_response("chore", None, "update a")]);
+
+  let output = repo
+    .gen_commit()
+    .args(["--model", &fixture.model(), "--reasoning-effort", "minimal"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert_eq!(fixture.requests()[0]["reasoning_effort"], "minimal");
+}
+
+#[test]
+fn diff_files_leave_out_ignored_paths() {
+  let repo = TestRepo::new();
+  repo.commit(&[("README.md", "# Demo\n")], "docs: add readme");
+  let patch: String = ["Cargo.lock", "docs/guide.md", "src/lib.rs"]
+    .iter()
+    .map(|path| {
+      format!("diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n@@ -1 +1 @@\n-old {path}\n+new {path}\n")
+    })
+    .collect();
+  let patch_path = repo.path().join(".git/change.patch");
+  std::fs::write(&patch_path, patch).expect("write patch");
+  let fixture = repo.mock_fixture(&[commit_response("fix", None, "update lib")]);
+
+  let output = repo
+    .gen_commit()
+    .args(["--model", &fixture.model(), "--no-analysis", "--no-cache"])
+    .args(["--ignore", "Cargo.lock,docs/", "--diff-file"])
+    .arg(&patch_path)
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  let user = fixture.requests()[0]["user"].as_str().unwrap().to_string();
+  assert!(user.contains("+new src/lib.rs"));
+  assert!(!user.contains("Cargo.lock"));
+  assert!(!user.contains("docs/guide.md"));
+}
+
+#[test]
+fn commit_hook_output_is_shown() {
+  let repo = TestRepo::new();
+  repo.commit(&[("src/lib.rs", "pub fn parse() {}\n")], "feat: add parser");
+  let hook = repo.path().join(".git/hooks/pre-commit");
+  std::fs::write(
+    &hook,
+    "#!/bin/sh\necho 'pre-commit: formatting checked' >&2\n",
+  )
+  .unwrap();
+  std::fs::set_permissions(&hook, std::os::unix::fs::PermissionsExt::from_mode(0o755)).unwrap();
+
+  repo.stage(&[("src/lib.rs", "pub fn parse() -> bool {\n  true\n}\n")]);
+  let fixture = repo.mock_fixture(&[commit_response("fix", None, "return a result")]);
+  let output = repo
+    .gen_commit()
+    .args(["--model", &fixture.model(), "--no-analysis", "--no-cache"])
+    .args(["--yes", "--no-edit"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(stderr(&output).contains("pre-commit: formatting checked"));
+  assert_eq!(repo.last_commit_message(), "fix: return a result");
+
+  std::fs::write(
+    &hook,
+    "#!/bin/sh\necho 'pre-commit: lint failed' >&2\nexit 3\n",
+  )
+  .unwrap();
+  repo.stage(&[("src/lib.rs", "pub fn parse() -> bool {\n  false\n}\n")]);
+  let fixture = repo.mock_fixture(&[commit_response("fix", None, "invert the result")]);
+  let output = repo
+    .gen_commit()
+    .args(["--model", &fixture.model(), "--no-analysis", "--no-cache"])
+    .args(["--yes", "--no-edit"])
+    .output()
+    .expect("run gen-commit");
+
+  assert_eq!(
+    output.status.code(),
+    Some(1),
+    "git exits with 1 when a hook fails"
+  );
+  let stderr = stderr(&output);
+  assert!(stderr.contains("pre-commit: lint failed"), "{stderr}");
+  assert!(
+    stderr.contains("Commit failed, see the git output above: git commit exited with status 1"),
+    "{stderr}"
+  );
+  assert_eq!(repo.last_commit_message(), "fix: return a result");
+}
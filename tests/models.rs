@@ -0,0 +1,148 @@
+mod common;
+
+use common::{TestRepo, commit_response, stderr, stdout};
+
+fn staged_repo() -> TestRepo {
+  let repo = TestRepo::new();
+  repo.commit(&[("a.txt", "a\n")], "chore: init");
+  repo.stage(&[("a.txt", "b\n")]);
+  repo
+}
+
+#[test]
+fn models_lists_the_catalog_and_configured_models() {
+  let repo = TestRepo::new();
+  repo.write(
+    ".gen-commit.toml",
+    "[models.\"azure:my-gpt-4o\"]\ncontext_window = 128000\nmax_output_tokens = 16384\ninput_price = 2.5\noutput_price = 10\n",
+  );
+
+  let output = repo
+    .gen_commit()
+    .arg("models")
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  let stdout = stdout(&output);
+  assert!(stdout.contains("claude-sonnet-4"));
+  assert!(stdout.contains("gemini-2.5-flash"));
+  let gpt = stdout
+    .lines()
+    .find(|line| line.trim_start().starts_with("gpt-4.1 "))
+    .expect("gpt-4.1 is listed");
+  assert!(gpt.contains("1047576") && gpt.contains("32768"));
+  assert!(stdout.contains("azure\n"));
+  assert!(stdout.contains("my-gpt-4o"));
+
+  let output = repo
+    .gen_commit()
+    .args(["models", "google"])
+    .output()
+    .expect("run gen-commit");
+  let stdout = common::stdout(&output);
+  assert!(stdout.contains("gemini-2.5-pro"));
+  assert!(!stdout.contains("gpt-4.1"));
+}
+
+#[test]
+fn max_tokens_above_the_output_limit_fails() {
+  let repo = staged_repo();
+
+  let output = repo
+    .gen_commit()
+    .args([
+      "--model",
+      "openai:gpt-4.1-2025-04-14",
+      "--max-tokens",
+      "40000",
+    ])
+    .args(["--no-analysis", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(!output.status.success());
+  assert!(
+    stderr(&output).contains("openai:gpt-4.1 generates at most 32768 output tokens"),
+    "{}",
+    stderr(&output)
+  );
+}
+
+#[test]
+fn max_tokens_with_a_reasoning_budget_does_not_overflow() {
+  let repo = staged_repo();
+
+  let output = repo
+    .gen_commit()
+    .args(["--model", "anthropic:claude-sonnet-4-5"])
+    .args(["--max-tokens", &u32::MAX.to_string()])
+    .args(["--reasoning-effort", "high", "--no-analysis", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(!output.status.success());
+  assert!(
+    stderr(&output).contains(&format!("but {} were requested", u32::MAX)),
+    "{}",
+    stderr(&output)
+  );
+}
+
+#[test]
+fn large_diffs_are_trimmed_to_the_context_window() {
+  let repo = TestRepo::new();
+  repo.commit(&[("small.txt", "a\n"), ("large.txt", "a\n")], "chore: init");
+  let large: String = (0..1500)
+    .map(|i| format!("generated line {i} of the large file\n"))
+    .collect();
+  repo.stage(&[("small.txt", "small change\n"), ("large.txt", &large)]);
+  let fixture = repo.mock_fixture(&[commit_response("chore", None, "update files")]);
+  repo.write(
+    ".gen-commit.toml",
+    &format!(
+      "[models.\"{}\"]\ncontext_window = 6000\nmax_output_tokens = 1000\n",
+      fixture.model()
+    ),
+  );
+
+  let output = repo
+    .gen_commit()
+    .args(["--model", &fixture.model(), "--no-analysis", "--no-cache"])
+    .arg("--dry-run")
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(stdout(&output).contains("left out 1 hunks of the diff"));
+
+  let user = fixture.requests()[0]["user"].as_str().unwrap().to_string();
+  assert!(user.contains("+small change"));
+  assert!(!user.contains("generated line 0"));
+  assert!(user.contains("1 more hunks omitted"));
+  assert!(user.contains("large.txt"), "the stat lists every file");
+}
+
+#[test]
+fn usage_reports_the_estimated_cost() {
+  let repo = staged_repo();
+  let fixture = repo.mock_fixture(&[commit_response("chore", None, "update a")]);
+  repo.write(
+    ".gen-commit.toml",
+    &format!(
+      "[models.\"{}\"]\ninput_price = 3.0\noutput_price = 15.0\n",
+      fixture.model()
+    ),
+  );
+
+  let output = repo
+    .gen_commit()
+    .args(["--model", &fixture.model(), "--no-analysis", "--no-cache"])
+    .arg("--dry-run")
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  // 100 input tokens at $3 and 20 output tokens at $15 per million
+  assert!(stdout(&output).contains("Estimated cost: $0.0006"));
+}
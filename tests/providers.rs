@@ -0,0 +1,662 @@
+mod common;
+
+use common::{StandIn, TestRepo, stderr, stdout};
+use serde_json::json;
+
+fn staged_repo() -> TestRepo {
+  let repo = TestRepo::new();
+  repo.commit(&[("src/lib.rs", "pub fn a() {}\n")], "feat: add a");
+  repo.stage(&[("src/lib.rs", "pub fn a() {}\npub fn b() {}\n")]);
+  repo
+}
+
+fn commit_json() -> String {
+  json!({
+    "type": "feat",
+    "scope": null,
+    "breaking": false,
+    "subject": "add b",
+    "body": ["- Add function b next to a"],
+    "footers": [],
+    "prefix": null,
+  })
+  .to_string()
+}
+
+#[test]
+fn anthropic_request_uses_a_forced_tool_and_a_cached_system_prompt() {
+  let repo = staged_repo();
+  let commit = serde_json::from_str::<serde_json::Value>(&commit_json()).unwrap();
+  let stand_in = StandIn::start(vec![json!({
+    "content": [{ "type": "tool_use", "id": "toolu_1", "name": "commit_message", "input": commit }],
+    "usage": {
+      "input_tokens": 50,
+      "output_tokens": 30,
+      "cache_creation_input_tokens": 0,
+      "cache_read_input_tokens": 2000,
+    },
+  })]);
+
+  let output = repo
+    .gen_commit()
+    .env("ANTHROPIC_API_KEY", "test-key")
+    .env("ANTHROPIC_BASE_URL", stand_in.url())
+    .args(["--model", "anthropic:claude-test", "--max-tokens", "700"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  let stdout = stdout(&output);
+  assert!(stdout.contains("feat: add b\n\n- Add function b next to a"));
+  assert!(stdout.contains("Cache read tokens: 2000"));
+
+  let requests = stand_in.requests();
+  assert_eq!(requests.len(), 1);
+  let request = &requests[0];
+  assert_eq!(request.path, "/v1/messages");
+  assert_eq!(request.header("x-api-key"), Some("test-key"));
+  assert_eq!(request.header("anthropic-version"), Some("2023-06-01"));
+
+  let body = &request.body;
+  assert_eq!(body["model"], "claude-test");
+  assert_eq!(body["max_tokens"], 700);
+  assert_eq!(body["stream"], false);
+  assert_eq!(body["system"][0]["type"], "text");
+  assert_eq!(body["system"][0]["cache_control"]["type"], "ephemeral");
+  assert_eq!(body["messages"][0]["role"], "user");
+  assert!(
+    body["messages"][0]["content"]
+      .as_str()
+      .unwrap()
+      .contains("+pub fn b() {}")
+  );
+  assert_eq!(body["tools"][0]["name"], "commit_message");
+  assert_eq!(body["tools"][0]["input_schema"]["type"], "object");
+  assert_eq!(
+    body["tool_choice"],
+    json!({ "type": "tool", "name": "commit_message" })
+  );
+}
+
+#[test]
+fn anthropic_analysis_request_has_no_tools() {
+  let repo = staged_repo();
+  let stand_in = StandIn::start(vec![
+    json!({
+      "content": [{ "type": "text", "text": "Adds function b." }],
+      "usage": { "input_tokens": 10, "output_tokens": 5 },
+    }),
+    json!({
+      "content": [{ "type": "text", "text": commit_json() }],
+      "usage": { "input_tokens": 10, "output_tokens": 5 },
+    }),
+  ]);
+
+  let output = repo
+    .gen_commit()
+    .env("ANTHROPIC_API_KEY", "test-key")
+    .env("ANTHROPIC_BASE_URL", stand_in.url())
+    .args([
+      "--model",
+      "anthropic:claude-test",
+      "--no-cache",
+      "--dry-run",
+    ])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(common::stdout(&output).contains("feat: add b"));
+
+  let requests = stand_in.requests();
+  assert_eq!(requests.len(), 2);
+  // The analysis prompt is too short to be cached
+  assert!(requests[0].body["system"][0].get("cache_control").is_none());
+  assert!(requests[0].body.get("tools").is_none());
+  assert!(requests[0].body.get("tool_choice").is_none());
+  assert!(
+    requests[1].body["messages"][0]["content"]
+      .as_str()
+      .unwrap()
+      .contains("Adds function b.")
+  );
+}
+
+#[test]
+fn openai_request_uses_a_strict_json_schema() {
+  let repo = staged_repo();
+  let stand_in = StandIn::start(vec![json!({
+    "output": [{ "type": "message", "content": [{ "type": "output_text", "text": commit_json() }] }],
+    "usage": {
+      "input_tokens": 1500,
+      "output_tokens": 40,
+      "total_tokens": 1540,
+      "input_tokens_details": { "cached_tokens": 1024 },
+    },
+  })]);
+
+  let output = repo
+    .gen_commit()
+    .env("OPENAI_API_KEY", "test-key")
+    .env("OPENAI_BASE_URL", stand_in.url())
+    .args(["--model", "openai:gpt-test"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  let stdout = stdout(&output);
+  assert!(stdout.contains("feat: add b"));
+  assert!(stdout.contains("Input tokens: 476"));
+  assert!(stdout.contains("Cache read tokens: 1024"));
+
+  let requests = stand_in.requests();
+  assert_eq!(requests.len(), 1);
+  let request = &requests[0];
+  assert_eq!(request.path, "/responses");
+  assert_eq!(request.header("authorization"), Some("Bearer test-key"));
+
+  let body = &request.body;
+  assert_eq!(body["model"], "gpt-test");
+  assert_eq!(body["input"][0]["role"], "system");
+  assert_eq!(body["input"][1]["role"], "user");
+  let format = &body["text"]["format"];
+  assert_eq!(format["type"], "json_schema");
+  assert_eq!(format["name"], "commit_message");
+  assert_eq!(format["strict"], true);
+  assert_eq!(format["schema"]["additionalProperties"], false);
+}
+
+#[test]
+fn api_errors_are_reported() {
+  let repo = staged_repo();
+  let stand_in = StandIn::start(vec![json!({ "error": "not an API response" })]);
+
+  let output = repo
+    .gen_commit()
+    .env("OPENAI_API_KEY", "test-key")
+    .env("OPENAI_BASE_URL", stand_in.url())
+    .args(["--model", "openai:gpt-test"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(!output.status.success());
+  assert!(stderr(&output).contains("FailedToParseResponse"));
+}
+
+#[test]
+fn requests_go_through_the_configured_proxy() {
+  let repo = staged_repo();
+  let stand_in = StandIn::start(vec![json!({
+    "content": [{ "type": "text", "text": commit_json() }],
+    "usage": { "input_tokens": 10, "output_tokens": 5 },
+  })]);
+  repo.write(
+    ".gen-commit.toml",
+    &format!(
+      "[providers.anthropic]\nbase_url = \"http://anthropic.example.test\"\n\n[network]\nproxy = \"{}\"\n",
+      stand_in.url()
+    ),
+  );
+
+  let output = repo
+    .gen_commit()
+    .env("ANTHROPIC_API_KEY", "test-key")
+    .args(["--model", "anthropic:claude-test"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  let requests = stand_in.requests();
+  assert_eq!(requests.len(), 1);
+  assert_eq!(
+    requests[0].path,
+    "http://anthropic.example.test/v1/messages"
+  );
+}
+
+#[test]
+fn connection_errors_include_their_cause() {
+  let repo = staged_repo();
+  // Bind and drop a listener to get a port nothing listens on
+  let port = std::net::TcpListener::bind("127.0.0.1:0")
+    .and_then(|listener| listener.local_addr())
+    .expect("free port")
+    .port();
+
+  let output = repo
+    .gen_commit()
+    .env("OPENAI_API_KEY", "test-key")
+    .env("OPENAI_BASE_URL", format!("http://127.0.0.1:{port}"))
+    .args(["--model", "openai:gpt-test"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(!output.status.success());
+  let stderr = stderr(&output);
+  assert!(stderr.contains("FailedToSend"), "{stderr}");
+  assert!(stderr.contains("Connection refused"), "{stderr}");
+}
+
+#[test]
+fn missing_ca_bundle_is_reported() {
+  let repo = staged_repo();
+  repo.write(
+    ".gen-commit.toml",
+    "[network]\nca_bundle = \"missing-ca.pem\"\n",
+  );
+
+  let output = repo
+    .gen_commit()
+    .env("OPENAI_API_KEY", "test-key")
+    .args(["--model", "openai:gpt-test"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(!output.status.success());
+  assert!(stderr(&output).contains("missing-ca.pem"));
+}
+
+#[test]
+fn openai_reasoning_models_get_reasoning_options_and_no_temperature() {
+  let repo = staged_repo();
+  let stand_in = StandIn::start(vec![json!({
+    "status": "completed",
+    "output": [
+      { "type": "reasoning", "id": "rs_1", "summary": [] },
+      { "type": "message", "content": [{ "type": "output_text", "text": commit_json() }] },
+    ],
+    "usage": {
+      "input_tokens": 100,
+      "output_tokens": 900,
+      "total_tokens": 1000,
+      "output_tokens_details": { "reasoning_tokens": 850 },
+    },
+  })]);
+
+  let output = repo
+    .gen_commit()
+    .env("OPENAI_API_KEY", "test-key")
+    .env("OPENAI_BASE_URL", stand_in.url())
+    .args(["--model", "openai:gpt-5", "--reasoning-effort", "low"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  let stdout = stdout(&output);
+  assert!(stdout.contains("feat: add b"));
+  assert!(stdout.contains("of which reasoning: 850"));
+
+  let body = &stand_in.requests()[0].body;
+  assert_eq!(body["reasoning"], json!({ "effort": "low" }));
+  assert_eq!(body["max_output_tokens"], 500 + 2048);
+  assert!(body.get("temperature").is_none());
+  assert!(body.get("max_tokens").is_none());
+}
+
+#[test]
+fn openai_chat_models_keep_temperature() {
+  let repo = staged_repo();
+  let stand_in = StandIn::start(vec![json!({
+    "output": [{ "type": "message", "content": [{ "type": "output_text", "text": commit_json() }] }],
+    "usage": { "input_tokens": 10, "output_tokens": 5, "total_tokens": 15 },
+  })]);
+
+  let output = repo
+    .gen_commit()
+    .env("OPENAI_API_KEY", "test-key")
+    .env("OPENAI_BASE_URL", stand_in.url())
+    .args(["--model", "openai:gpt-4.1"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  let body = &stand_in.requests()[0].body;
+  assert_eq!(body["max_output_tokens"], 500);
+  assert!(body["temperature"].is_number());
+  assert!(body.get("reasoning").is_none());
+}
+
+fn azure_stand_in() -> StandIn {
+  StandIn::start(vec![json!({
+    "output": [{ "type": "message", "content": [{ "type": "output_text", "text": commit_json() }] }],
+    "usage": { "input_tokens": 10, "output_tokens": 5, "total_tokens": 15 },
+  })])
+}
+
+#[test]
+fn azure_requests_use_the_resource_endpoint_and_api_key_header() {
+  let repo = staged_repo();
+  let stand_in = azure_stand_in();
+
+  let output = repo
+    .gen_commit()
+    .env("AZURE_OPENAI_API_KEY", "azure-key")
+    .env("AZURE_OPENAI_ENDPOINT", format!("{}/", stand_in.url()))
+    .args(["--model", "azure:prod-gpt-4o"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(stdout(&output).contains("feat: add b"));
+
+  let request = &stand_in.requests()[0];
+  assert_eq!(
+    request.path,
+    "/openai/responses?api-version=2025-04-01-preview"
+  );
+  assert_eq!(request.header("api-key"), Some("azure-key"));
+  assert!(request.header("authorization").is_none());
+  assert_eq!(request.body["model"], "prod-gpt-4o");
+  assert!(request.body["temperature"].is_number());
+
+  let output = repo
+    .gen_commit()
+    .env("AZURE_OPENAI_API_KEY", "azure-key")
+    .env("AZURE_OPENAI_ENDPOINT", stand_in.url())
+    .env("AZURE_OPENAI_API_VERSION", "2025-06-01")
+    .args(["--model", "azure:prod-gpt-4o"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert_eq!(
+    stand_in.requests()[1].path,
+    "/openai/responses?api-version=2025-06-01"
+  );
+}
+
+#[test]
+fn azure_reasoning_effort_needs_a_reasoning_deployment() {
+  let repo = staged_repo();
+  let stand_in = azure_stand_in();
+
+  let output = repo
+    .gen_commit()
+    .env("AZURE_OPENAI_API_KEY", "azure-key")
+    .env("AZURE_OPENAI_ENDPOINT", stand_in.url())
+    .args(["--model", "azure:prod-gpt-4o", "--reasoning-effort", "low"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(!output.status.success());
+  assert!(stderr(&output).contains("azure:prod-gpt-4o does not support --reasoning-effort"));
+  assert!(stand_in.requests().is_empty());
+
+  // Deployments named like a reasoning model, or listed as one, accept it
+  repo.write(
+    ".gen-commit.toml",
+    "[models.\"azure:prod-reasoner\"]\nreasoning = true\ntemperature = false\n",
+  );
+  for deployment in ["azure:o4-mini", "azure:prod-reasoner"] {
+    let output = repo
+      .gen_commit()
+      .env("AZURE_OPENAI_API_KEY", "azure-key")
+      .env("AZURE_OPENAI_ENDPOINT", stand_in.url())
+      .args(["--model", deployment, "--reasoning-effort", "low"])
+      .args(["--no-analysis", "--no-cache", "--dry-run"])
+      .output()
+      .expect("run gen-commit");
+
+    assert!(output.status.success(), "{deployment}: {}", stderr(&output));
+    let body = stand_in.requests().pop().unwrap().body;
+    assert_eq!(
+      body["reasoning"],
+      json!({ "effort": "low" }),
+      "{deployment}"
+    );
+    assert!(body.get("temperature").is_none(), "{deployment}");
+  }
+}
+
+#[test]
+fn openai_incomplete_responses_are_reported() {
+  let repo = staged_repo();
+  let stand_in = StandIn::start(vec![json!({
+    "status": "incomplete",
+    "incomplete_details": { "reason": "max_output_tokens" },
+    "output": [{ "type": "reasoning", "id": "rs_1", "summary": [] }],
+    "usage": { "input_tokens": 10, "output_tokens": 8692, "total_tokens": 8702 },
+  })]);
+
+  let output = repo
+    .gen_commit()
+    .env("OPENAI_API_KEY", "test-key")
+    .env("OPENAI_BASE_URL", stand_in.url())
+    .args(["--model", "openai:o4-mini"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(!output.status.success());
+  assert!(stderr(&output).contains("incomplete (max_output_tokens)"));
+}
+
+#[test]
+fn reasoning_effort_is_rejected_for_models_that_do_not_reason() {
+  let repo = staged_repo();
+
+  let output = repo
+    .gen_commit()
+    .env("OPENAI_API_KEY", "test-key")
+    .args(["--model", "openai:gpt-4.1", "--reasoning-effort", "high"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(!output.status.success());
+  assert!(stderr(&output).contains("openai:gpt-4.1 does not support --reasoning-effort"));
+}
+
+#[test]
+fn anthropic_thinking_lets_the_model_choose_the_tool() {
+  let repo = staged_repo();
+  let commit = serde_json::from_str::<serde_json::Value>(&commit_json()).unwrap();
+  let stand_in = StandIn::start(vec![json!({
+    "content": [
+      { "type": "thinking", "thinking": "The diff adds b.", "signature": "sig" },
+      { "type": "text", "text": "I'll record the commit message." },
+      { "type": "tool_use", "id": "toolu_1", "name": "commit_message", "input": commit },
+    ],
+    "usage": { "input_tokens": 50, "output_tokens": 300 },
+  })]);
+
+  let output = repo
+    .gen_commit()
+    .env("ANTHROPIC_API_KEY", "test-key")
+    .env("ANTHROPIC_BASE_URL", stand_in.url())
+    .args(["--model", "anthropic:claude-sonnet-4-20250514"])
+    .args(["--reasoning-effort", "medium"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(stdout(&output).contains("feat: add b"));
+
+  let body = &stand_in.requests()[0].body;
+  assert_eq!(
+    body["thinking"],
+    json!({ "type": "enabled", "budget_tokens": 8192 })
+  );
+  assert_eq!(body["max_tokens"], 500 + 8192);
+  assert!(body.get("temperature").is_none());
+  assert_eq!(body["tool_choice"], json!({ "type": "auto" }));
+  assert_eq!(body["system"][0]["cache_control"]["type"], "ephemeral");
+  assert_eq!(
+    body["system"][1],
+    json!({ "type": "text", "text": "Answer only by calling the `commit_message` tool." })
+  );
+}
+
+#[test]
+fn gemini_request_uses_a_system_instruction_and_a_json_schema() {
+  let repo = staged_repo();
+  let stand_in = StandIn::start(vec![json!({
+    "candidates": [{
+      "content": {
+        "role": "model",
+        "parts": [
+          { "text": "The diff adds a function b.", "thought": true },
+          { "text": commit_json() },
+        ],
+      },
+    }],
+    "usageMetadata": {
+      "promptTokenCount": 1200,
+      "candidatesTokenCount": 40,
+      "thoughtsTokenCount": 300,
+      "totalTokenCount": 1540,
+      "cachedContentTokenCount": 1000,
+    },
+  })]);
+
+  let output = repo
+    .gen_commit()
+    .env("GEMINI_API_KEY", "gemini-key")
+    .env("GEMINI_BASE_URL", format!("{}/", stand_in.url()))
+    .args([
+      "--model",
+      "google:gemini-2.5-flash",
+      "--reasoning-effort",
+      "low",
+    ])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  let stdout = stdout(&output);
+  assert!(stdout.contains("feat: add b\n\n- Add function b next to a"));
+  assert!(!stdout.contains("The diff adds a function b."));
+  assert!(stdout.contains("Input tokens: 200"));
+  assert!(stdout.contains("Cache read tokens: 1000"));
+  assert!(stdout.contains("of which reasoning: 300"));
+
+  let requests = stand_in.requests();
+  assert_eq!(requests.len(), 1);
+  let request = &requests[0];
+  assert_eq!(request.path, "/models/gemini-2.5-flash:generateContent");
+  assert_eq!(request.header("x-goog-api-key"), Some("gemini-key"));
+
+  let body = &request.body;
+  let system = &body["systemInstruction"];
+  assert!(system.get("role").is_none());
+  assert!(
+    system["parts"][0]["text"]
+      .as_str()
+      .unwrap()
+      .contains("conventional commit")
+  );
+  assert!(system["parts"][0].get("thought").is_none());
+  assert_eq!(body["contents"][0]["role"], "user");
+  assert!(
+    body["contents"][0]["parts"][0]["text"]
+      .as_str()
+      .unwrap()
+      .contains("+pub fn b() {}")
+  );
+
+  let config = &body["generationConfig"];
+  assert_eq!(config["responseMimeType"], "application/json");
+  assert_eq!(config["responseJsonSchema"]["type"], "object");
+  assert_eq!(config["thinkingConfig"], json!({ "thinkingBudget": 2048 }));
+  assert_eq!(config["maxOutputTokens"], 500 + 2048);
+}
+
+/// A `cmd:fake` provider running `script` with `sh`, which gets the path of the repository's
+/// `.git` directory as `$1` for files it reads or writes
+fn fake_command(repo: &TestRepo, script: &str) {
+  let git_dir = repo.path().join(".git");
+  std::fs::write(git_dir.join("fake.sh"), script).expect("write script");
+  repo.write(
+    ".gen-commit.toml",
+    &format!(
+      "[providers.cmd.fake]\ncommand = \"sh\"\nargs = [\"{0}/fake.sh\", \"{0}\"]\n",
+      git_dir.display()
+    ),
+  );
+}
+
+#[test]
+fn cmd_provider_exchanges_json_over_stdio() {
+  let repo = staged_repo();
+  let response = json!({
+    "message": commit_json(),
+    "usage": { "input_tokens": 120, "output_tokens": 30 },
+  });
+  std::fs::write(repo.path().join(".git/response.json"), response.to_string())
+    .expect("write response");
+  fake_command(
+    &repo,
+    "cat > \"$1/request.json\"\ncat \"$1/response.json\"\n",
+  );
+
+  let output = repo
+    .gen_commit()
+    .args(["--model", "cmd:fake", "--max-tokens", "700"])
+    .args(["--no-analysis", "--no-cache", "--dry-run"])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(output.status.success(), "{}", stderr(&output));
+  let stdout = stdout(&output);
+  assert!(stdout.contains("feat: add b\n\n- Add function b next to a"));
+  assert!(stdout.contains("Input tokens: 120"));
+
+  let request = std::fs::read_to_string(repo.path().join(".git/request.json")).unwrap();
+  let request: serde_json::Value = serde_json::from_str(&request).unwrap();
+  assert_eq!(request["model"], "fake");
+  assert_eq!(request["max_tokens"], 700);
+  assert_eq!(request["messages"][0]["role"], "user");
+  assert!(
+    request["messages"][0]["content"]
+      .as_str()
+      .unwrap()
+      .contains("+pub fn b() {}")
+  );
+  assert_eq!(request["schema"]["type"], "object");
+  assert!(
+    request["system"]
+      .as_str()
+      .unwrap()
+      .contains("Respond only with a JSON object matching this JSON schema")
+  );
+}
+
+#[test]
+fn cmd_provider_failing_before_reading_input_reports_its_stderr() {
+  let repo = TestRepo::new();
+  repo.commit(&[("data.txt", "a\n")], "chore: init");
+  // Far more than a pipe buffer, so writing it fails once the command is gone
+  let large: String = (0..20_000).map(|i| format!("line {i}\n")).collect();
+  repo.stage(&[("data.txt", &large)]);
+  fake_command(&repo, "echo 'gateway unavailable' >&2\nexit 3\n");
+
+  let output = repo
+    .gen_commit()
+    .args([
+      "--model",
+      "cmd:fake",
+      "--no-analysis",
+      "--no-cache",
+      "--dry-run",
+    ])
+    .output()
+    .expect("run gen-commit");
+
+  assert!(!output.status.success());
+  let stderr = stderr(&output);
+  assert!(stderr.contains("exited with exit status: 3"), "{stderr}");
+  assert!(stderr.contains("gateway unavailable"), "{stderr}");
+}
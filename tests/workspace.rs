@@ -0,0 +1,171 @@
+mod common;
+
+use common::{TestRepo, commit_response, stderr, stdout};
+
+/// The `Affected packages` line of the commit prompt for the staged changes
+fn affected_packages(repo: &TestRepo) -> String {
+  let fixture = repo.mock_fixture(&[commit_response("chore", None, "update")]);
+
+  let output = repo
+    .gen_commit()
+    .args(["--model", &fixture.model(), "--no-analysis", "--no-cache"])
+    .arg("--dry-run")
+    .output()
+    .expect("run gen-commit");
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  let user = fixture.requests()[0]["user"].as_str().unwrap().to_string();
+  user
+    .lines()
+    .find_map(|line| line.strip_prefix("Affected packages: "))
+    .expect("prompt lists the affected packages")
+    .to_string()
+}
+
+#[test]
+fn nx_project_json_takes_precedence_over_package_json() {
+  let repo = TestRepo::new();
+  repo.commit(
+    &[
+      ("nx.json", "{}\n"),
+      (
+        "libs/core/package.json",
+        r#"{ "name": "core-pkg", "nx": {} }"#,
+      ),
+      ("libs/core/project.json", r#"{ "name": "core-project" }"#),
+      ("libs/core/src/index.ts", "export {};\n"),
+    ],
+    "chore: init",
+  );
+  repo.stage(&[("libs/core/src/index.ts", "export const a = 1;\n")]);
+
+  assert_eq!(affected_packages(&repo), "core-project");
+}
+
+#[test]
+fn nx_root_project_only_owns_its_source_root() {
+  let cases = [
+    // (changed files, affected packages)
+    (&["libs/ui/index.ts", "tools/build.ts"][..], "ui"),
+    (&["src/main.ts", "tools/build.ts"][..], "app"),
+  ];
+
+  for (changed, expected) in cases {
+    let repo = TestRepo::new();
+    repo.commit(
+      &[
+        ("nx.json", "{}\n"),
+        ("package.json", r#"{ "name": "workspace", "nx": {} }"#),
+        (
+          "project.json",
+          r#"{ "name": "app", "root": ".", "sourceRoot": "./src" }"#,
+        ),
+        ("src/main.ts", "export {};\n"),
+        ("libs/ui/project.json", r#"{ "name": "ui" }"#),
+        ("libs/ui/index.ts", "export {};\n"),
+        ("tools/build.ts", "export {};\n"),
+      ],
+      "chore: init",
+    );
+    let staged: Vec<(&str, &str)> = changed
+      .iter()
+      .map(|path| (*path, "export const a = 1;\n"))
+      .collect();
+    repo.stage(&staged);
+
+    assert_eq!(affected_packages(&repo), expected, "{changed:?}");
+  }
+}
+
+#[test]
+fn go_work_uses_the_root_module_and_skips_other_directives() {
+  let repo = TestRepo::new();
+  repo.commit(
+    &[
+      ("go.work", "go 1.22\n\nuse .\nuse ./api\nuser ./ignored\n"),
+      ("go.mod", "module example.com/app\n"),
+      ("main.go", "package main\n"),
+      ("api/go.mod", "module example.com/api\n"),
+      ("api/api.go", "package api\n"),
+      ("ignored/go.mod", "module example.com/ignored\n"),
+      ("ignored/ignored.go", "package ignored\n"),
+    ],
+    "chore: init",
+  );
+  repo.stage(&[
+    ("main.go", "package main\n\nfunc main() {}\n"),
+    ("api/api.go", "package api\n\nfunc A() {}\n"),
+    ("ignored/ignored.go", "package ignored\n\nfunc B() {}\n"),
+  ]);
+
+  assert_eq!(affected_packages(&repo), "api, app");
+}
+
+#[test]
+fn pnpm_workspace_packages_are_detected() {
+  let repo = TestRepo::new();
+  repo.commit(
+    &[
+      (
+        "pnpm-workspace.yaml",
+        "packages:\n  - 'packages/*'\n  - '!packages/private'\n",
+      ),
+      ("packages/ui/package.json", r#"{ "name": "@acme/ui" }"#),
+      ("packages/ui/index.ts", "export {};\n"),
+      ("packages/private/package.json", r#"{ "name": "private" }"#),
+      ("packages/private/index.ts", "export {};\n"),
+    ],
+    "chore: init",
+  );
+  repo.stage(&[
+    ("packages/ui/index.ts", "export const a = 1;\n"),
+    ("packages/private/index.ts", "export const b = 1;\n"),
+  ]);
+
+  assert_eq!(affected_packages(&repo), "ui");
+}
+
+#[test]
+fn turborepo_packages_come_from_the_package_json_workspaces() {
+  let repo = TestRepo::new();
+  repo.commit(
+    &[
+      ("turbo.json", r#"{ "tasks": { "build": {} } }"#),
+      ("package.json", r#"{ "workspaces": ["apps/*"] }"#),
+      ("apps/web/package.json", r#"{ "name": "web" }"#),
+      ("apps/web/index.ts", "export {};\n"),
+    ],
+    "chore: init",
+  );
+  repo.stage(&[("apps/web/index.ts", "export const a = 1;\n")]);
+
+  assert_eq!(affected_packages(&repo), "web");
+}
+
+#[test]
+fn turbo_json_without_workspaces_is_reported() {
+  let repo = TestRepo::new();
+  repo.commit(
+    &[
+      ("turbo.json", r#"{ "tasks": { "build": {} } }"#),
+      ("apps/web/package.json", r#"{ "name": "web" }"#),
+      ("apps/web/index.ts", "export {};\n"),
+    ],
+    "chore: init",
+  );
+  repo.stage(&[("apps/web/index.ts", "export const a = 1;\n")]);
+  let fixture = repo.mock_fixture(&[commit_response("chore", None, "update")]);
+
+  let output = repo
+    .gen_commit()
+    .args(["--model", &fixture.model(), "--no-analysis", "--no-cache"])
+    .arg("--dry-run")
+    .output()
+    .expect("run gen-commit");
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(
+    stdout(&output).contains("Warning: turbo.json found without"),
+    "{}",
+    stdout(&output)
+  );
+}